@@ -0,0 +1,188 @@
+#[cfg(not(test))]
+use chrono::Utc;
+#[cfg(test)]
+use mock_chrono::Utc;
+
+use std::time::Instant;
+
+use chrono::{DateTime, TimeDelta};
+
+/// Source of the current time for scheduling
+pub trait Clock {
+    fn now(&self) -> DateTime<chrono::Utc>;
+
+    /// Converts a delay on this clock into real milliseconds to wait
+    fn real_ms(&self, ms: i64) -> i64 {
+        ms
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<chrono::Utc> {
+        Utc::now()
+    }
+}
+
+/// Virtual clock starting at a given time and running `speed` times faster than real time
+pub struct SimulatedClock {
+    start: DateTime<chrono::Utc>,
+    real_start: Instant,
+    speed: f64,
+}
+
+impl SimulatedClock {
+    pub fn new(start: DateTime<chrono::Utc>, speed: f64) -> Self {
+        Self {
+            start,
+            real_start: Instant::now(),
+            speed,
+        }
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> DateTime<chrono::Utc> {
+        let elapsed_ms = self.real_start.elapsed().as_secs_f64() * 1000.0 * self.speed;
+        self.start + TimeDelta::milliseconds(elapsed_ms as i64)
+    }
+
+    fn real_ms(&self, ms: i64) -> i64 {
+        // A zero delay would disarm the timer instead of firing it immediately
+        ((ms as f64 / self.speed).ceil() as i64).max(1)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn simulated_clock() {
+        let start = DateTime::from_timestamp(0, 0).unwrap();
+        let clock = SimulatedClock::new(start, 3600.0);
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert!(clock.now() - start >= TimeDelta::seconds(36));
+        assert_eq!(clock.real_ms(3_600_000), 1000);
+        assert_eq!(clock.real_ms(100), 1);
+        assert_eq!(SystemClock.real_ms(100), 100);
+    }
+}
+
+#[cfg(test)]
+pub mod mock_chrono {
+    use std::cell::Cell;
+
+    use chrono::{
+        DateTime, FixedOffset, MappedLocalTime, NaiveDate, NaiveDateTime, NaiveTime, Offset,
+        TimeZone,
+    };
+
+    thread_local! {
+        static DATE: Cell<Option<DateTime<chrono::FixedOffset>>> = const { Cell::new(None) };
+        /// UTC time from which on `Local` uses another offset than the one of `DATE`
+        static TRANSITION: Cell<Option<(NaiveDateTime, FixedOffset)>> = const { Cell::new(None) };
+    }
+
+    #[derive(Clone)]
+    pub struct Utc;
+
+    impl Utc {
+        pub fn now() -> DateTime<chrono::Utc> {
+            DATE.with(|date| date.get().unwrap().with_timezone(&chrono::Utc))
+        }
+    }
+
+    mod inner {
+        use super::*;
+
+        fn date_offset() -> FixedOffset {
+            DATE.with(|date| date.get().unwrap().offset().fix())
+        }
+
+        pub(super) fn offset_from_utc_datetime(
+            utc_time: &NaiveDateTime,
+        ) -> MappedLocalTime<FixedOffset> {
+            MappedLocalTime::Single(match TRANSITION.get() {
+                Some((at, offset)) if *utc_time >= at => offset,
+                _ => date_offset(),
+            })
+        }
+
+        pub(super) fn offset_from_local_datetime(
+            local_time: &NaiveDateTime,
+        ) -> MappedLocalTime<FixedOffset> {
+            let Some((_, transition_offset)) = TRANSITION.get() else {
+                return MappedLocalTime::Single(date_offset());
+            };
+            let mut offsets: Vec<FixedOffset> = [date_offset(), transition_offset]
+                .into_iter()
+                .filter(|offset| {
+                    offset_from_utc_datetime(&(*local_time - *offset)).single() == Some(*offset)
+                })
+                .collect();
+            // The larger offset is the earlier UTC time
+            offsets.sort_by_key(|offset| -offset.local_minus_utc());
+            offsets.dedup();
+            match offsets.as_slice() {
+                [] => MappedLocalTime::None,
+                [offset] => MappedLocalTime::Single(*offset),
+                [earliest, latest, ..] => MappedLocalTime::Ambiguous(*earliest, *latest),
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct Local;
+
+    impl Local {
+        pub fn now() -> DateTime<chrono::Local> {
+            DATE.with(|date| {
+                let localdate: DateTime<chrono::Local> = date.get().unwrap().into();
+                let offset = date.get().unwrap().offset().fix();
+                DateTime::from_naive_utc_and_offset(localdate.naive_utc(), offset)
+            })
+        }
+    }
+
+    impl TimeZone for Local {
+        type Offset = FixedOffset;
+
+        fn from_offset(_offset: &FixedOffset) -> Local {
+            Local
+        }
+
+        #[allow(deprecated)]
+        fn offset_from_local_date(&self, local: &NaiveDate) -> MappedLocalTime<FixedOffset> {
+            // Get the offset at local midnight.
+            self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_local_datetime(
+            &self,
+            local: &NaiveDateTime,
+        ) -> MappedLocalTime<FixedOffset> {
+            inner::offset_from_local_datetime(local)
+        }
+
+        #[allow(deprecated)]
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            // Get the offset at midnight.
+            self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            inner::offset_from_utc_datetime(utc).unwrap()
+        }
+    }
+
+    pub fn set(val: DateTime<chrono::FixedOffset>) {
+        DATE.with(|date| date.set(Some(val)));
+    }
+
+    /// Makes `Local` switch to `offset` at `at`, like a daylight saving time transition
+    pub fn set_transition(at: DateTime<chrono::Utc>, offset: FixedOffset) {
+        TRANSITION.set(Some((at.naive_utc(), offset)));
+    }
+}
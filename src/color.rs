@@ -1,25 +1,80 @@
+/// Locus the white point follows as the temperature changes
+#[derive(Clone, Copy, PartialEq, Default)]
+#[cfg_attr(test, derive(Debug))]
+pub enum WhitePointModel {
+    /// Planckian locus of a blackbody radiator
+    #[default]
+    Blackbody,
+    /// CIE D-series daylight locus, following the blackbody below 4000K where it is undefined
+    Daylight,
+}
+
 #[derive(Clone, Copy, PartialEq)]
 #[cfg_attr(test, derive(Debug))]
 pub struct Color {
     pub temperature: u16,
-    pub gamma: f64,
+    /// Red, green and blue gamma
+    pub gamma: [f64; 3],
     pub brightness: f64,
     pub inverted: bool,
+    /// Red, green and blue multipliers on top of the white point
+    pub gain: [f64; 3],
+    /// Red, green and blue black levels added to the ramps, as a fraction of the full range
+    pub offset: [f64; 3],
+    pub white_point_model: WhitePointModel,
 }
 
 impl Default for Color {
     fn default() -> Self {
         Self {
             temperature: 6500,
-            gamma: 1.0,
+            gamma: [1.0; 3],
             brightness: 1.0,
             inverted: false,
+            gain: [1.0; 3],
+            offset: [0.0; 3],
+            white_point_model: WhitePointModel::default(),
         }
     }
 }
 
-fn map_intensity(v: f64, white: f64, color: Color, v_max_gamma: f64) -> u16 {
-    ((v * white).powf(color.gamma) * v_max_gamma) as u16
+impl Color {
+    /// Blends towards `other`, where `a` is 0.0 for `self` and 1.0 for `other`
+    pub fn interpolate(self, other: Color, a: f64) -> Color {
+        let mix = |x: f64, y: f64| (1.0 - a) * x + a * y;
+        Color {
+            temperature: mix(self.temperature as f64, other.temperature as f64).round() as u16,
+            gamma: [0, 1, 2].map(|i| mix(self.gamma[i], other.gamma[i])),
+            brightness: mix(self.brightness, other.brightness),
+            inverted: if a < 0.5 {
+                self.inverted
+            } else {
+                other.inverted
+            },
+            gain: [0, 1, 2].map(|i| mix(self.gain[i], other.gain[i])),
+            offset: [0, 1, 2].map(|i| mix(self.offset[i], other.offset[i])),
+            white_point_model: if a < 0.5 {
+                self.white_point_model
+            } else {
+                other.white_point_model
+            },
+        }
+    }
+
+    /// Formats the gamma as one value when every channel has the same, else as `red/green/blue`
+    pub fn gamma_str(&self) -> String {
+        let [r, g, b] = self.gamma;
+        if r == g && g == b {
+            r.to_string()
+        } else {
+            format!("{}/{}/{}", r, g, b)
+        }
+    }
+}
+
+fn map_intensity(v: f64, white: f64, offset: f64, gamma: f64, v_max_gamma: f64) -> u16 {
+    // Saturates to the range of `u16`
+    ((v * white).powf(gamma) * v_max_gamma + offset * u16::MAX as f64) as u16
 }
 
 pub fn fill_color_ramp(
@@ -29,67 +84,242 @@ pub fn fill_color_ramp(
     ramp_size: usize,
     color: Color,
 ) {
-    let color_i = ((color.temperature as usize - 1000) / 100) * 3;
-    let [white_r, white_g, white_b] = interpolate_color(
-        (color.temperature % 100) as f64 / 100.0,
-        &BLACKBODY_COLOR[color_i..],
-        &BLACKBODY_COLOR[(color_i + 3)..],
-    );
+    let white = white_point(color.white_point_model, color.temperature as f64);
 
     let v_max = u16::MAX as f64 * color.brightness;
-    let v_max_gamma = v_max.powf(1.0 - color.gamma);
     let step = v_max / (ramp_size - 1) as f64;
-    for i in 0..ramp_size {
-        let v = step * i as f64;
-        let index = if color.inverted { ramp_size - 1 - i } else { i };
-        r[index] = map_intensity(v, white_r, color, v_max_gamma);
-        g[index] = map_intensity(v, white_g, color, v_max_gamma);
-        b[index] = map_intensity(v, white_b, color, v_max_gamma);
+    for (channel, ramp) in [r, g, b].into_iter().enumerate() {
+        let gamma = color.gamma[channel];
+        let v_max_gamma = v_max.powf(1.0 - gamma);
+        for i in 0..ramp_size {
+            let v = step * i as f64;
+            let index = if color.inverted { ramp_size - 1 - i } else { i };
+            ramp[index] = map_intensity(
+                v,
+                white[channel] * color.gain[channel],
+                color.offset[channel],
+                gamma,
+                v_max_gamma,
+            );
+        }
+    }
+}
+
+/// Lowest supported color temperature in Kelvin
+pub const TEMPERATURE_MIN: u16 = 700;
+/// Highest supported color temperature in Kelvin
+pub const TEMPERATURE_MAX: u16 = 25000;
+/// Temperature that leaves colors untouched
+const TEMPERATURE_NEUTRAL: f64 = 6500.0;
+/// Lowest temperature the CIE daylight locus is defined for
+const DAYLIGHT_MIN: f64 = 4000.0;
+
+/// Returns the red, green and blue multipliers of the white point at `temperature`
+///
+/// Channels are relative to the white point of `model` at 6500K and scaled so the largest is 1.0.
+fn white_point(model: WhitePointModel, temperature: f64) -> [f64; 3] {
+    let xyz = |temperature| match model {
+        WhitePointModel::Blackbody => blackbody_xyz(temperature),
+        WhitePointModel::Daylight => daylight_xyz(temperature),
+    };
+    let neutral = linear_srgb(xyz(TEMPERATURE_NEUTRAL));
+    let rgb = linear_srgb(xyz(temperature));
+    let linear = [0, 1, 2].map(|i| (rgb[i] / neutral[i]).max(0.0));
+    let max = linear.into_iter().fold(0.0, f64::max);
+    linear.map(|v| srgb_encode(v / max))
+}
+
+/// Computes the CIE XYZ color of a blackbody at `temperature` in Kelvin, unnormalized
+fn blackbody_xyz(temperature: f64) -> [f64; 3] {
+    // Second radiation constant in m·K
+    const C2: f64 = 1.438_776_877e-2;
+
+    let mut xyz = [0.0; 3];
+    for wavelength in (380..=780).step_by(5) {
+        let wavelength = wavelength as f64;
+        let meters = wavelength * 1e-9;
+        let radiance = 1.0 / (meters.powi(5) * ((C2 / (meters * temperature)).exp() - 1.0));
+        for (sum, matching) in xyz.iter_mut().zip(color_matching(wavelength)) {
+            *sum += radiance * matching;
+        }
     }
+
+    xyz
+}
+
+/// Computes the CIE XYZ color of the CIE daylight illuminant at `temperature` in Kelvin, with a
+/// luminance of 1.0
+///
+/// Below 4000K the blackbody locus is followed instead, shifted to meet the daylight one.
+fn daylight_xyz(temperature: f64) -> [f64; 3] {
+    let [x, y] = if temperature < DAYLIGHT_MIN {
+        let [x, y] = chromaticity(blackbody_xyz(temperature));
+        let [x_min, y_min] = daylight_chromaticity(DAYLIGHT_MIN);
+        let [x_blackbody, y_blackbody] = chromaticity(blackbody_xyz(DAYLIGHT_MIN));
+        [x + x_min - x_blackbody, y + y_min - y_blackbody]
+    } else {
+        daylight_chromaticity(temperature)
+    };
+    [x / y, 1.0, (1.0 - x - y) / y]
+}
+
+/// Computes the CIE xy chromaticity of the CIE daylight illuminant, from 4000K to 25000K
+fn daylight_chromaticity(t: f64) -> [f64; 2] {
+    let x = if t <= 7000.0 {
+        -4.6070e9 / t.powi(3) + 2.9678e6 / t.powi(2) + 0.09911e3 / t + 0.244_063
+    } else {
+        -2.0064e9 / t.powi(3) + 1.9018e6 / t.powi(2) + 0.24748e3 / t + 0.237_040
+    };
+    [x, -3.0 * x * x + 2.87 * x - 0.275]
+}
+
+/// Returns the CIE xy chromaticity of a CIE XYZ color
+fn chromaticity([x, y, z]: [f64; 3]) -> [f64; 2] {
+    [x / (x + y + z), y / (x + y + z)]
 }
 
-fn interpolate_color(a: f64, c1: &[f64], c2: &[f64]) -> [f64; 3] {
+/// Converts a CIE XYZ color to linear sRGB
+fn linear_srgb([x, y, z]: [f64; 3]) -> [f64; 3] {
     [
-        (1.0 - a) * c1[0] + a * c2[0],
-        (1.0 - a) * c1[1] + a * c2[1],
-        (1.0 - a) * c1[2] + a * c2[2],
+        3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z,
+        -0.969_266_0 * x + 1.876_010_8 * y + 0.041_556_0 * z,
+        0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z,
     ]
 }
 
-const BLACKBODY_COLOR: &[f64] = &[
-    1.00000000, 0.18172716, 0.00000000, 1.00000000, 0.25503671, 0.00000000, 1.00000000, 0.30942099,
-    0.00000000, 1.00000000, 0.35357379, 0.00000000, 1.00000000, 0.39091524, 0.00000000, 1.00000000,
-    0.42322816, 0.00000000, 1.00000000, 0.45159884, 0.00000000, 1.00000000, 0.47675916, 0.00000000,
-    1.00000000, 0.49923747, 0.00000000, 1.00000000, 0.51943421, 0.00000000, 1.00000000, 0.54360078,
-    0.08679949, 1.00000000, 0.56618736, 0.14065513, 1.00000000, 0.58734976, 0.18362641, 1.00000000,
-    0.60724493, 0.22137978, 1.00000000, 0.62600248, 0.25591950, 1.00000000, 0.64373109, 0.28819679,
-    1.00000000, 0.66052319, 0.31873863, 1.00000000, 0.67645822, 0.34786758, 1.00000000, 0.69160518,
-    0.37579588, 1.00000000, 0.70602449, 0.40267128, 1.00000000, 0.71976951, 0.42860152, 1.00000000,
-    0.73288760, 0.45366838, 1.00000000, 0.74542112, 0.47793608, 1.00000000, 0.75740814, 0.50145662,
-    1.00000000, 0.76888303, 0.52427322, 1.00000000, 0.77987699, 0.54642268, 1.00000000, 0.79041843,
-    0.56793692, 1.00000000, 0.80053332, 0.58884417, 1.00000000, 0.81024551, 0.60916971, 1.00000000,
-    0.81957693, 0.62893653, 1.00000000, 0.82854786, 0.64816570, 1.00000000, 0.83717703, 0.66687674,
-    1.00000000, 0.84548188, 0.68508786, 1.00000000, 0.85347859, 0.70281616, 1.00000000, 0.86118227,
-    0.72007777, 1.00000000, 0.86860704, 0.73688797, 1.00000000, 0.87576611, 0.75326132, 1.00000000,
-    0.88267187, 0.76921169, 1.00000000, 0.88933596, 0.78475236, 1.00000000, 0.89576933, 0.79989606,
-    1.00000000, 0.90198230, 0.81465502, 1.00000000, 0.90963069, 0.82838210, 1.00000000, 0.91710889,
-    0.84190889, 1.00000000, 0.92441842, 0.85523742, 1.00000000, 0.93156127, 0.86836903, 1.00000000,
-    0.93853986, 0.88130458, 1.00000000, 0.94535695, 0.89404470, 1.00000000, 0.95201559, 0.90658983,
-    1.00000000, 0.95851906, 0.91894041, 1.00000000, 0.96487079, 0.93109690, 1.00000000, 0.97107439,
-    0.94305985, 1.00000000, 0.97713351, 0.95482993, 1.00000000, 0.98305189, 0.96640795, 1.00000000,
-    0.98883326, 0.97779486, 1.00000000, 0.99448139, 0.98899179, 1.00000000, 1.00000000, 1.00000000,
-    0.98947904, 0.99348723, 1.00000000, 0.97940448, 0.98722715, 1.00000000, 0.96975025, 0.98120637,
-    1.00000000, 0.96049223, 0.97541240, 1.00000000, 0.95160805, 0.96983355, 1.00000000, 0.94303638,
-    0.96443333, 1.00000000, 0.93480451, 0.95923080, 1.00000000, 0.92689056, 0.95421394, 1.00000000,
-    0.91927697, 0.94937330, 1.00000000, 0.91194747, 0.94470005, 1.00000000, 0.90488690, 0.94018594,
-    1.00000000, 0.89808115, 0.93582323, 1.00000000, 0.89151710, 0.93160469, 1.00000000, 0.88518247,
-    0.92752354, 1.00000000, 0.87906581, 0.92357340, 1.00000000, 0.87315640, 0.91974827, 1.00000000,
-    0.86744421, 0.91604254, 1.00000000, 0.86191983, 0.91245088, 1.00000000, 0.85657444, 0.90896831,
-    1.00000000, 0.85139976, 0.90559011, 1.00000000, 0.84638799, 0.90231183, 1.00000000, 0.84153180,
-    0.89912926, 1.00000000, 0.83682430, 0.89603843, 1.00000000, 0.83225897, 0.89303558, 1.00000000,
-    0.82782969, 0.89011714, 1.00000000, 0.82353066, 0.88727974, 1.00000000, 0.81935641, 0.88452017,
-    1.00000000, 0.81530175, 0.88183541, 1.00000000, 0.81136180, 0.87922257, 1.00000000, 0.80753191,
-    0.87667891, 1.00000000, 0.80380769, 0.87420182, 1.00000000, 0.80018497, 0.87178882, 1.00000000,
-    0.79665980, 0.86943756, 1.00000000, 0.79322843, 0.86714579, 1.00000000, 0.78988728, 0.86491137,
-    1.00000000, 0.78663296, 0.86273225, 1.00000000,
-];
+/// Approximates the CIE 1931 2° color matching functions at `wavelength` in nanometers
+///
+/// Multi-lobe fit from Wyman, Sloan and Shirley, "Simple Analytic Approximations to the CIE XYZ
+/// Color Matching Functions" (2013).
+fn color_matching(wavelength: f64) -> [f64; 3] {
+    let gaussian = |mean: f64, below: f64, above: f64| {
+        let sigma = if wavelength < mean { below } else { above };
+        (-0.5 * ((wavelength - mean) / sigma).powi(2)).exp()
+    };
+    [
+        1.056 * gaussian(599.8, 37.9, 31.0) + 0.362 * gaussian(442.0, 16.0, 26.7)
+            - 0.065 * gaussian(501.1, 20.4, 26.2),
+        0.821 * gaussian(568.8, 46.9, 40.5) + 0.286 * gaussian(530.9, 16.3, 31.1),
+        1.217 * gaussian(437.0, 11.8, 36.0) + 0.681 * gaussian(459.0, 26.0, 13.8),
+    ]
+}
+
+/// Applies the sRGB transfer function to a linear value from 0.0 to 1.0
+fn srgb_encode(v: f64) -> f64 {
+    if v <= 0.003_130_8 {
+        12.92 * v
+    } else if v >= 1.0 {
+        // Exactly, as the formula rounds it down
+        1.0
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(actual: [f64; 3], expected: [f64; 3]) {
+        for (a, e) in actual.into_iter().zip(expected) {
+            assert!(
+                (a - e).abs() < 0.03,
+                "{:?} is not close to {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn neutral() {
+        assert_eq!(
+            white_point(WhitePointModel::Blackbody, TEMPERATURE_NEUTRAL),
+            [1.0; 3]
+        );
+
+        let (mut r, mut g, mut b) = ([0; 256], [0; 256], [0; 256]);
+        fill_color_ramp(&mut r, &mut g, &mut b, 256, Color::default());
+        for ramp in [r, g, b] {
+            assert_eq!(ramp[0], 0);
+            assert_eq!(ramp[255], u16::MAX);
+            assert!(ramp.is_sorted());
+        }
+    }
+
+    #[test]
+    fn blackbody() {
+        // Values of the table this model replaces
+        assert_close(
+            white_point(WhitePointModel::Blackbody, 1000.0),
+            [1.0, 0.182, 0.0],
+        );
+        assert_close(
+            white_point(WhitePointModel::Blackbody, 3000.0),
+            [1.0, 0.720, 0.429],
+        );
+        assert_close(
+            white_point(WhitePointModel::Blackbody, 4500.0),
+            [1.0, 0.868, 0.737],
+        );
+        assert_close(
+            white_point(WhitePointModel::Blackbody, 10000.0),
+            [0.790, 0.865, 1.0],
+        );
+
+        // Warmer is redder and cooler is bluer, without steps in between
+        let mut previous = white_point(WhitePointModel::Blackbody, TEMPERATURE_MIN as f64);
+        for temperature in (TEMPERATURE_MIN + 10..=TEMPERATURE_MAX).step_by(10) {
+            let white = white_point(WhitePointModel::Blackbody, temperature as f64);
+            assert!(white[2] - white[0] >= previous[2] - previous[0]);
+            assert!((white[1] - previous[1]).abs() < 0.03);
+            previous = white;
+        }
+    }
+
+    #[test]
+    fn daylight() {
+        assert_eq!(
+            white_point(WhitePointModel::Daylight, TEMPERATURE_NEUTRAL),
+            [1.0; 3]
+        );
+        // D50 and D100
+        assert_close(
+            white_point(WhitePointModel::Daylight, 5000.0),
+            [1.0, 0.921, 0.806],
+        );
+        assert_close(
+            white_point(WhitePointModel::Daylight, 10000.0),
+            [0.790, 0.865, 1.0],
+        );
+
+        let mut previous = white_point(WhitePointModel::Daylight, TEMPERATURE_MIN as f64);
+        for temperature in (TEMPERATURE_MIN + 10..=TEMPERATURE_MAX).step_by(10) {
+            let white = white_point(WhitePointModel::Daylight, temperature as f64);
+            assert!(white[2] - white[0] >= previous[2] - previous[0]);
+            assert!((white[1] - previous[1]).abs() < 0.03);
+            previous = white;
+        }
+    }
+
+    #[test]
+    fn boundaries() {
+        for temperature in [TEMPERATURE_MIN, 1000, 10000, TEMPERATURE_MAX] {
+            let (mut r, mut g, mut b) = ([0; 256], [0; 256], [0; 256]);
+            let color = Color {
+                temperature,
+                ..Color::default()
+            };
+            fill_color_ramp(&mut r, &mut g, &mut b, 256, color);
+            assert_eq!(r[255].max(b[255]), u16::MAX);
+        }
+        assert_close(
+            white_point(WhitePointModel::Blackbody, TEMPERATURE_MIN as f64),
+            [1.0, 0.0, 0.0],
+        );
+        assert_close(
+            white_point(WhitePointModel::Blackbody, TEMPERATURE_MAX as f64),
+            [0.648, 0.760, 1.0],
+        );
+    }
+}
@@ -1,24 +1,84 @@
-use std::fmt::{self, Display};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use chrono::{NaiveTime, TimeDelta, Timelike};
-use serde::Deserialize;
+use chrono::{Datelike, NaiveDate, NaiveTime, TimeDelta, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+use sunrise::{DawnType, SolarEvent};
 use thiserror::Error;
 use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
 
-use crate::color::Color;
+use crate::{
+    color::{Color, TEMPERATURE_MAX, TEMPERATURE_MIN, WhitePointModel},
+    lut::Lut,
+    output::OutputColors,
+    schedule::ColorMode,
+    zone::Zone,
+};
+
+/// Gamma of every channel, or of red, green and blue
+#[derive(Clone, Copy, Deserialize, Serialize, Debug)]
+#[serde(untagged)]
+enum GammaConfig {
+    All(f64),
+    Channels([f64; 3]),
+}
+
+impl GammaConfig {
+    fn channels(self) -> [f64; 3] {
+        match self {
+            Self::All(gamma) => [gamma; 3],
+            Self::Channels(channels) => channels,
+        }
+    }
+}
+
+fn validate_gamma(gamma: &GammaConfig) -> Result<(), ValidationError> {
+    gamma
+        .channels()
+        .iter()
+        .all(|gamma| *gamma > 0.0)
+        .then_some(())
+        .ok_or_else(|| ValidationError::new("gamma"))
+}
 
 #[derive(Deserialize, Debug, Validate)]
+#[serde(rename_all = "kebab-case")]
 struct ColorConfig {
-    #[validate(range(min = 1000, max = 10000))]
+    #[validate(range(min = TEMPERATURE_MIN, max = TEMPERATURE_MAX))]
     temperature: Option<u16>,
-    #[validate(range(min = 0.0))]
-    gamma: Option<f64>,
+    #[validate(custom(function = "validate_gamma"))]
+    gamma: Option<GammaConfig>,
+    /// Channel gammas, taking precedence over `gamma`
+    #[validate(range(exclusive_min = 0.0))]
+    gamma_red: Option<f64>,
+    #[validate(range(exclusive_min = 0.0))]
+    gamma_green: Option<f64>,
+    #[validate(range(exclusive_min = 0.0))]
+    gamma_blue: Option<f64>,
     #[validate(range(min = 0.0))]
     brightness: Option<f64>,
     inverted: Option<bool>,
+    /// Channel multipliers on top of the white point of `temperature`
+    #[validate(range(min = 0.0))]
+    red: Option<f64>,
+    #[validate(range(min = 0.0))]
+    green: Option<f64>,
+    #[validate(range(min = 0.0))]
+    blue: Option<f64>,
+    /// Channel black levels as a fraction of the full range
+    #[validate(range(min = -1.0, max = 1.0))]
+    red_offset: Option<f64>,
+    #[validate(range(min = -1.0, max = 1.0))]
+    green_offset: Option<f64>,
+    #[validate(range(min = -1.0, max = 1.0))]
+    blue_offset: Option<f64>,
 }
 
-#[derive(Deserialize, Debug, Validate)]
+#[derive(Clone, Deserialize, Debug, Validate)]
 #[cfg_attr(test, derive(PartialEq))]
 pub struct Location {
     #[validate(range(min = -90.0, max = 90.0))]
@@ -27,18 +87,68 @@ pub struct Location {
     pub longitude: f64,
 }
 
+const SOLAR_ANCHORS: [(&str, SolarEvent); 8] = [
+    ("sunrise", SolarEvent::Sunrise),
+    ("sunset", SolarEvent::Sunset),
+    ("civil-dawn", SolarEvent::Dawn(DawnType::Civil)),
+    ("civil-dusk", SolarEvent::Dusk(DawnType::Civil)),
+    ("nautical-dawn", SolarEvent::Dawn(DawnType::Nautical)),
+    ("nautical-dusk", SolarEvent::Dusk(DawnType::Nautical)),
+    (
+        "astronomical-dawn",
+        SolarEvent::Dawn(DawnType::Astronomical),
+    ),
+    (
+        "astronomical-dusk",
+        SolarEvent::Dusk(DawnType::Astronomical),
+    ),
+];
+
+/// Returns the name of a solar event as written in the config
+pub fn solar_event_name(event: SolarEvent) -> &'static str {
+    SOLAR_ANCHORS
+        .iter()
+        .find(|(_, anchor)| *anchor == event)
+        .map_or("elevation", |(name, _)| name)
+}
+
+fn parse_offset(offset_str: &str) -> Option<TimeDelta> {
+    let sign = match offset_str.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let naive_time = NaiveTime::parse_from_str(&offset_str[1..], "%H:%M").ok()?;
+    Some(
+        (TimeDelta::hours(naive_time.hour() as i64)
+            + TimeDelta::minutes(naive_time.minute() as i64))
+            * sign,
+    )
+}
+
 fn parse_schedule(time_str: &str) -> Result<ScheduleType, ValidationError> {
-    let first_char = time_str.chars().next();
-    Ok(match first_char {
-        Some(c) if c == '+' || c == '-' => {
-            let sign = if first_char == Some('+') { 1 } else { -1 };
-            let naive_time = NaiveTime::parse_from_str(&time_str[1..], "%H:%M")
-                .map_err(|_| ValidationError::new("relative_time"))?;
-            let time_delta = (TimeDelta::hours(naive_time.hour() as i64)
-                + TimeDelta::minutes(naive_time.minute() as i64))
-                * sign;
-            ScheduleType::Relative(time_delta)
-        }
+    if let Some((event, offset_str)) = SOLAR_ANCHORS
+        .iter()
+        .find_map(|(name, event)| time_str.strip_prefix(name).map(|rest| (*event, rest)))
+    {
+        let time_delta = match offset_str {
+            "" => TimeDelta::zero(),
+            _ => parse_offset(offset_str).ok_or_else(|| ValidationError::new("anchor_time"))?,
+        };
+        return Ok(ScheduleType::Solar(event, time_delta));
+    }
+    if let Some(elevation_str) = time_str.strip_prefix("elevation:") {
+        return elevation_str
+            .parse::<f64>()
+            .ok()
+            .filter(|elevation| (-90.0..=90.0).contains(elevation))
+            .map(ScheduleType::Elevation)
+            .ok_or_else(|| ValidationError::new("elevation"));
+    }
+    Ok(match time_str.chars().next() {
+        Some('+' | '-') => ScheduleType::Relative(
+            parse_offset(time_str).ok_or_else(|| ValidationError::new("relative_time"))?,
+        ),
         _ => ScheduleType::Fixed(
             NaiveTime::parse_from_str(time_str, "%H:%M")
                 .map_err(|_| ValidationError::new("fixed_time"))?,
@@ -50,17 +160,259 @@ fn validate_schedule(time_str: &str) -> Result<(), ValidationError> {
 }
 
 #[derive(Deserialize, Debug, Validate)]
+struct PeriodConfig {
+    name: String,
+    #[validate(custom(function = "validate_schedule"))]
+    start: String,
+    #[serde(flatten)]
+    #[validate(nested)]
+    color: ColorConfig,
+}
+
+#[derive(Deserialize, Debug)]
+struct OutputConfig {
+    enabled: Option<bool>,
+    /// 1D `.cube` or `r,g,b` CSV file composed with the colors of the output
+    lut: Option<String>,
+    /// Color overrides keyed by `day`, `night` or a period name
+    #[serde(flatten)]
+    colors: BTreeMap<String, ColorConfig>,
+}
+
+/// `[output.PATTERN]` tables in the order of the file, so that the first matching pattern wins
+#[derive(Debug, Default)]
+struct OutputTables(Vec<(String, OutputConfig)>);
+
+impl<'de> Deserialize<'de> for OutputTables {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TablesVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for TablesVisitor {
+            type Value = OutputTables;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a table of output patterns")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut tables = Vec::new();
+                while let Some(table) = map.next_entry()? {
+                    tables.push(table);
+                }
+                Ok(OutputTables(tables))
+            }
+        }
+
+        deserializer.deserialize_map(TablesVisitor)
+    }
+}
+
+impl Validate for OutputTables {
+    /// Validates the colors of every table, keying errors by `"PATTERN"` and mode name as written
+    /// in the file
+    fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        for (pattern, output) in &self.0 {
+            let mut output_errors = ValidationErrors::new();
+            for (name, color) in &output.colors {
+                if let Err(color_errors) = color.validate() {
+                    output_errors.0.insert(
+                        name.clone().into(),
+                        ValidationErrorsKind::Struct(Box::new(color_errors)),
+                    );
+                }
+            }
+            if !output_errors.is_empty() {
+                errors.0.insert(
+                    format!("{:?}", pattern).into(),
+                    ValidationErrorsKind::Struct(Box::new(output_errors)),
+                );
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+pub fn parse_duration(duration_str: &str) -> Result<TimeDelta, ValidationError> {
+    let error = || ValidationError::new("duration");
+    let mut time_delta = TimeDelta::zero();
+    let mut rest = duration_str;
+    if rest.is_empty() {
+        return Err(error());
+    }
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).ok_or_else(error)?;
+        let value: i64 = rest[..digits].parse().map_err(|_| error())?;
+        let unit = rest[digits..].chars().next().ok_or_else(error)?;
+        time_delta += match unit {
+            'h' => TimeDelta::try_hours(value),
+            'm' => TimeDelta::try_minutes(value),
+            's' => TimeDelta::try_seconds(value),
+            _ => None,
+        }
+        .ok_or_else(error)?;
+        rest = &rest[digits + 1..];
+    }
+    Ok(time_delta)
+}
+fn validate_duration(duration_str: &str) -> Result<(), ValidationError> {
+    parse_duration(duration_str).map(|_| ())
+}
+
+/// Parses `sun`, `day`, `night` or `fixed:HH:MM-HH:MM`
+fn parse_polar_fallback(fallback_str: &str) -> Result<PolarFallback, ValidationError> {
+    match fallback_str {
+        "sun" => Ok(PolarFallback::Sun),
+        "day" => Ok(PolarFallback::Day),
+        "night" => Ok(PolarFallback::Night),
+        _ => fallback_str
+            .strip_prefix("fixed:")
+            .and_then(|times| times.split_once('-'))
+            .and_then(|(day, night)| {
+                Some(PolarFallback::Fixed(
+                    NaiveTime::parse_from_str(day, "%H:%M").ok()?,
+                    NaiveTime::parse_from_str(night, "%H:%M").ok()?,
+                ))
+            })
+            .ok_or_else(|| ValidationError::new("polar_fallback")),
+    }
+}
+fn validate_polar_fallback(fallback_str: &str) -> Result<(), ValidationError> {
+    parse_polar_fallback(fallback_str).map(|_| ())
+}
+
+/// Parses `blackbody` or `daylight`
+fn parse_white_point_model(model_str: &str) -> Result<WhitePointModel, ValidationError> {
+    match model_str {
+        "blackbody" => Ok(WhitePointModel::Blackbody),
+        "daylight" => Ok(WhitePointModel::Daylight),
+        _ => Err(ValidationError::new("white_point_model")),
+    }
+}
+fn validate_white_point_model(model_str: &str) -> Result<(), ValidationError> {
+    parse_white_point_model(model_str).map(|_| ())
+}
+
+/// Day and night starts replacing those of `[schedule]` on some days of the week
+#[derive(Deserialize, Debug, Validate)]
+struct WeekdayConfig {
+    #[validate(custom(function = "validate_schedule"))]
+    day: Option<String>,
+    #[validate(custom(function = "validate_schedule"))]
+    night: Option<String>,
+}
+
+fn validate_date(date_str: &str) -> Result<(), ValidationError> {
+    NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
+        .map(|_| ())
+        .map_err(|_| ValidationError::new("date"))
+}
+
+/// Day and night starts replacing the usual ones on a range of dates or the dates of a file
+#[derive(Deserialize, Debug, Validate)]
+#[serde(rename_all = "kebab-case")]
+struct ExceptionConfig {
+    #[validate(custom(function = "validate_date"))]
+    from: Option<String>,
+    /// Last date of the range, `from` when unset
+    #[validate(custom(function = "validate_date"))]
+    to: Option<String>,
+    /// File listing one `YYYY-MM-DD` date per line
+    dates_file: Option<String>,
+    #[validate(custom(function = "validate_schedule"))]
+    day: Option<String>,
+    #[validate(custom(function = "validate_schedule"))]
+    night: Option<String>,
+}
+
+/// Resolves a leading `~/` to the home directory and other relative paths against `dir`
+fn resolve_path(dir: &Path, path_str: &str) -> PathBuf {
+    match (path_str.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(relative), Some(home)) => home.join(relative),
+        _ => dir.join(path_str),
+    }
+}
+
+/// Reads one `YYYY-MM-DD` date per line, skipping empty lines and `#` comments
+fn read_dates_file(path: &Path) -> anyhow::Result<Vec<NaiveDate>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|error| anyhow::anyhow!("Fail to read file {:?}, {}", path, error))?;
+
+    let mut dates = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        dates.push(NaiveDate::parse_from_str(line, "%Y-%m-%d").map_err(|_| {
+            anyhow::anyhow!(
+                "Invalid date {:?} on line {} of {:?}, expected 'YYYY-MM-DD'",
+                line,
+                index + 1,
+                path
+            )
+        })?);
+    }
+    Ok(dates)
+}
+
+fn validate_timezone(name: &str) -> Result<(), ValidationError> {
+    Zone::named(name)
+        .map(|_| ())
+        .ok_or_else(|| ValidationError::new("timezone"))
+}
+
+#[derive(Deserialize, Debug, Validate)]
+#[serde(rename_all = "kebab-case")]
 pub struct ScheduleConfig {
     #[validate(custom(function = "validate_schedule"))]
     day: Option<String>,
     #[validate(custom(function = "validate_schedule"))]
     night: Option<String>,
+    #[validate(custom(function = "validate_duration"))]
+    transition: Option<String>,
+    #[validate(custom(function = "validate_polar_fallback"))]
+    polar_fallback: Option<String>,
+    /// Overrides for Saturday and Sunday
+    #[validate(nested)]
+    weekend: Option<WeekdayConfig>,
+    /// Overrides for single days of the week, taking precedence over `weekend`
+    #[validate(nested)]
+    mon: Option<WeekdayConfig>,
+    #[validate(nested)]
+    tue: Option<WeekdayConfig>,
+    #[validate(nested)]
+    wed: Option<WeekdayConfig>,
+    #[validate(nested)]
+    thu: Option<WeekdayConfig>,
+    #[validate(nested)]
+    fri: Option<WeekdayConfig>,
+    #[validate(nested)]
+    sat: Option<WeekdayConfig>,
+    #[validate(nested)]
+    sun: Option<WeekdayConfig>,
+    /// Overrides for dates, taking precedence over the days of the week
+    #[validate(nested)]
+    exception: Option<Vec<ExceptionConfig>>,
 }
 
 #[derive(Deserialize, Debug, Validate)]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
 pub struct RawConfig {
+    /// Zone of the fixed times, the session's own when unset
+    #[validate(custom(function = "validate_timezone"))]
+    timezone: Option<String>,
+    /// Locus followed by the white point of every color, `blackbody` when unset
+    #[validate(custom(function = "validate_white_point_model"))]
+    white_point_model: Option<String>,
     #[validate(nested)]
     day: Option<ColorConfig>,
     #[validate(nested)]
@@ -69,13 +421,27 @@ pub struct RawConfig {
     location: Option<Location>,
     #[validate(nested)]
     schedule: Option<ScheduleConfig>,
+    #[validate(nested)]
+    period: Option<Vec<PeriodConfig>>,
+    #[validate(nested)]
+    output: Option<OutputTables>,
+    /// Directory relative file paths are resolved against
+    #[serde(skip)]
+    dir: PathBuf,
 }
 
 #[derive(Error, Debug)]
 #[cfg_attr(test, derive(PartialEq))]
+#[allow(clippy::enum_variant_names)]
 enum ConfigError {
     ValidationError(ValidationErrors),
     LocationError,
+    ElevationError,
+    WeekdayError,
+    /// Name of the offending period and what is wrong with it
+    PeriodError(String, &'static str),
+    OutputError,
+    ExceptionError,
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -109,8 +475,39 @@ impl Display for ConfigError {
                                 "range" if error.params.contains_key("min") => {
                                     format!("greater than {}", error.params["min"])
                                 }
-                                "fixed_time" => "in format 'HH:MM'".to_string(),
+                                "range" if error.params.contains_key("exclusive_min") => {
+                                    format!("greater than {}", error.params["exclusive_min"])
+                                }
+                                "fixed_time" => format!(
+                                    "in format 'HH:MM' or one of {}",
+                                    SOLAR_ANCHORS
+                                        .map(|(name, _)| format!("'{}'", name))
+                                        .join(", ")
+                                ),
                                 "relative_time" => "in format '+HH:MM' or '-HH:MM'".to_string(),
+                                "anchor_time" => {
+                                    "in format 'ANCHOR', 'ANCHOR+HH:MM' or 'ANCHOR-HH:MM'"
+                                        .to_string()
+                                }
+                                "elevation" => {
+                                    "in format 'elevation:DEGREES' with DEGREES in range -90-90"
+                                        .to_string()
+                                }
+                                "duration" => "in format like '1h30m', '45m' or '30s'".to_string(),
+                                "date" => "in format 'YYYY-MM-DD'".to_string(),
+                                "gamma" => "a number or a list of 3 numbers, all greater than 0"
+                                    .to_string(),
+                                "timezone" => {
+                                    "a time zone of the system tzdata like 'Europe/London'"
+                                        .to_string()
+                                }
+                                "white_point_model" => {
+                                    "one of 'blackbody' or 'daylight'".to_string()
+                                }
+                                "polar_fallback" => {
+                                    "one of 'sun', 'day', 'night' or 'fixed:HH:MM-HH:MM'"
+                                        .to_string()
+                                }
                                 _ => return Err(std::fmt::Error),
                             };
                             writeln!(
@@ -138,7 +535,24 @@ impl Display for ConfigError {
             Self::ValidationError(v_e) => print_errors(v_e, f, String::new()),
             Self::LocationError => writeln!(
                 f,
-                "[location] is required when [schedule.day] or [schedule.night] is unset"
+                "[location] is required when [schedule.day], [schedule.night] or a [[period]] start is unset or follows the sun"
+            ),
+            Self::ElevationError => writeln!(
+                f,
+                "[schedule.day] and [schedule.night] must both be elevations when either is, with day above night"
+            ),
+            Self::WeekdayError => writeln!(
+                f,
+                "[schedule.weekend] and [schedule.mon] to [schedule.sun] cannot be used with elevations"
+            ),
+            Self::PeriodError(name, reason) => writeln!(f, "[[period]] `{}` {}", name, reason),
+            Self::ExceptionError => writeln!(
+                f,
+                "[[schedule.exception]] needs `from` with an optional later `to`, or `dates-file`, and cannot be used with elevations"
+            ),
+            Self::OutputError => writeln!(
+                f,
+                "[output.NAME] may only contain `enabled`, `lut`, [day], [night] or tables named after a [[period]]"
             ),
         }
     }
@@ -149,6 +563,15 @@ impl RawConfig {
         toml::from_str(file)
     }
 
+    /// Resolves the relative file paths of the config against `dir`, the working directory by
+    /// default
+    pub fn relative_to(self, dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            ..self
+        }
+    }
+
     pub fn check(self) -> anyhow::Result<Config> {
         self.validate()
             .map_err(ConfigError::ValidationError)
@@ -157,21 +580,73 @@ impl RawConfig {
     }
 
     fn parse(self) -> anyhow::Result<Config> {
-        fn apply_default_color(color: Option<ColorConfig>) -> Color {
-            let default = Color::default();
+        fn apply_default_color(color: Option<ColorConfig>, default: Color) -> Color {
             color.map_or(default, |c| Color {
                 temperature: c.temperature.unwrap_or(default.temperature),
-                gamma: c.gamma.unwrap_or(default.gamma),
+                gamma: {
+                    let [r, g, b] = c.gamma.map_or(default.gamma, GammaConfig::channels);
+                    [
+                        c.gamma_red.unwrap_or(r),
+                        c.gamma_green.unwrap_or(g),
+                        c.gamma_blue.unwrap_or(b),
+                    ]
+                },
                 brightness: c.brightness.unwrap_or(default.brightness),
                 inverted: c.inverted.unwrap_or(default.inverted),
+                gain: [
+                    c.red.unwrap_or(default.gain[0]),
+                    c.green.unwrap_or(default.gain[1]),
+                    c.blue.unwrap_or(default.gain[2]),
+                ],
+                offset: [
+                    c.red_offset.unwrap_or(default.offset[0]),
+                    c.green_offset.unwrap_or(default.offset[1]),
+                    c.blue_offset.unwrap_or(default.offset[2]),
+                ],
+                white_point_model: default.white_point_model,
             })
         }
 
-        let day_color = apply_default_color(self.day);
-        let night_color = apply_default_color(self.night);
+        let default_color = Color {
+            white_point_model: self
+                .white_point_model
+                .as_deref()
+                .map_or(Ok(WhitePointModel::default()), parse_white_point_model)?,
+            ..Color::default()
+        };
+        let day_color = apply_default_color(self.day, default_color);
+        let night_color = apply_default_color(self.night, default_color);
+
+        let mut periods = Vec::new();
+        let mut period_types = Vec::new();
+        for period in self.period.unwrap_or_default() {
+            let period_type = parse_schedule(&period.start)?;
+            let reason = if matches!(period_type, ScheduleType::Elevation(_)) {
+                Some("must start at 'HH:MM', '+HH:MM', '-HH:MM' or a solar anchor")
+            } else if ["day", "night", "enabled", "lut"].contains(&period.name.as_str()) {
+                // Reserved by the modes and the keys of [output.NAME]
+                Some("cannot be named `day`, `night`, `enabled` or `lut`")
+            } else if periods.iter().any(|p: &Period| p.name == period.name) {
+                Some("has the name of another [[period]]")
+            } else {
+                None
+            };
+            if let Some(reason) = reason {
+                Err(ConfigError::PeriodError(period.name.clone(), reason))?
+            }
+            periods.push(Period {
+                name: period.name,
+                color: apply_default_color(Some(period.color), default_color),
+            });
+            period_types.push(period_type);
+        }
 
         let day_type: ScheduleType;
         let night_type: ScheduleType;
+        let mut transition = TimeDelta::zero();
+        let mut polar_fallback = PolarFallback::Sun;
+        let mut weekdays = [None; 7];
+        let mut exceptions = Vec::new();
         match self.schedule {
             None => {
                 day_type = ScheduleType::Auto;
@@ -187,31 +662,197 @@ impl RawConfig {
                 }
                 day_type = resolve_schedule_str(schedule.day)?;
                 night_type = resolve_schedule_str(schedule.night)?;
+                if let Some(duration_str) = schedule.transition {
+                    transition = parse_duration(&duration_str)?;
+                }
+                if let Some(fallback_str) = schedule.polar_fallback {
+                    polar_fallback = parse_polar_fallback(&fallback_str)?;
+                }
+
+                let weekday_configs = [
+                    (schedule.mon, "schedule.mon"),
+                    (schedule.tue, "schedule.tue"),
+                    (schedule.wed, "schedule.wed"),
+                    (schedule.thu, "schedule.thu"),
+                    (schedule.fri, "schedule.fri"),
+                    (schedule.sat, "schedule.sat"),
+                    (schedule.sun, "schedule.sun"),
+                ];
+                for (index, (weekday_config, table)) in weekday_configs.into_iter().enumerate() {
+                    let weekend_config = schedule.weekend.as_ref().filter(|_| index >= 5);
+                    if weekday_config.is_none() && weekend_config.is_none() {
+                        continue;
+                    }
+                    let resolve_weekday_str =
+                        |field: fn(&WeekdayConfig) -> &Option<String>,
+                         base: ScheduleType|
+                         -> anyhow::Result<(ScheduleType, &'static str)> {
+                            match [
+                                (weekday_config.as_ref(), table),
+                                (weekend_config, "schedule.weekend"),
+                            ]
+                            .into_iter()
+                            .find_map(|(config, table)| Some((field(config?).as_ref()?, table)))
+                            {
+                                Some((time_str, table)) => Ok((parse_schedule(time_str)?, table)),
+                                None => Ok((base, "schedule")),
+                            }
+                        };
+                    let (day, day_table) = resolve_weekday_str(|config| &config.day, day_type)?;
+                    let (night, night_table) =
+                        resolve_weekday_str(|config| &config.night, night_type)?;
+                    weekdays[index] = Some(WeekdaySchedule {
+                        day,
+                        night,
+                        day_table,
+                        night_table,
+                    });
+                }
+
+                for exception in schedule.exception.unwrap_or_default() {
+                    let parse_date = |date_str: &str| -> anyhow::Result<NaiveDate> {
+                        Ok(NaiveDate::parse_from_str(date_str, "%Y-%m-%d")?)
+                    };
+                    let mut dates = Vec::new();
+                    match (&exception.from, &exception.to) {
+                        (Some(from), to) => {
+                            let from = parse_date(from)?;
+                            let to = to.as_deref().map_or(Ok(from), parse_date)?;
+                            if from > to {
+                                Err(ConfigError::ExceptionError)?
+                            }
+                            dates.push((from, to));
+                        }
+                        (None, Some(_)) => Err(ConfigError::ExceptionError)?,
+                        (None, None) if exception.dates_file.is_none() => {
+                            Err(ConfigError::ExceptionError)?
+                        }
+                        (None, None) => (),
+                    }
+                    if let Some(path_str) = &exception.dates_file {
+                        dates.extend(
+                            read_dates_file(&resolve_path(&self.dir, path_str))?
+                                .into_iter()
+                                .map(|date| (date, date)),
+                        );
+                    }
+                    let parse_optional = |time_str: &Option<String>| {
+                        time_str.as_deref().map(parse_schedule).transpose()
+                    };
+                    exceptions.push(Exception {
+                        dates,
+                        day: parse_optional(&exception.day)?,
+                        night: parse_optional(&exception.night)?,
+                    });
+                }
+            }
+        }
+
+        let zone = match self.timezone {
+            Some(name) => Zone::named(&name).ok_or_else(|| ValidationError::new("timezone"))?,
+            None => Zone::Local,
+        };
+
+        let mut outputs = Vec::new();
+        for (pattern, mut output) in self.output.unwrap_or_default().0 {
+            let mut mode_color =
+                |name: &str, base: Color| apply_default_color(output.colors.remove(name), base);
+            let mut colors = vec![
+                mode_color("day", day_color),
+                mode_color("night", night_color),
+            ];
+            for period in &periods {
+                colors.push(mode_color(&period.name, period.color));
+            }
+            if !output.colors.is_empty() {
+                Err(ConfigError::OutputError)?
             }
+            let lut = match output.lut {
+                Some(path_str) => Some(Arc::new(Lut::read(&resolve_path(&self.dir, &path_str))?)),
+                None => None,
+            };
+            outputs.push(Output {
+                pattern,
+                colors: output.enabled.unwrap_or(true).then_some(colors),
+                lut,
+            });
         }
 
-        if !(day_type.is_fixed() && night_type.is_fixed()) && self.location.is_none() {
+        let exception_types: Vec<ScheduleType> = exceptions
+            .iter()
+            .flat_map(|exception| [exception.day, exception.night])
+            .flatten()
+            .collect();
+        if !(day_type.is_fixed()
+            && night_type.is_fixed()
+            && period_types.iter().all(ScheduleType::is_fixed)
+            && weekdays
+                .iter()
+                .flatten()
+                .all(|weekday| weekday.day.is_fixed() && weekday.night.is_fixed())
+            && exception_types.iter().all(ScheduleType::is_fixed))
+            && self.location.is_none()
+        {
             Err(ConfigError::LocationError)?
         }
 
+        if weekdays.iter().flatten().any(|weekday| {
+            [day_type, night_type, weekday.day, weekday.night]
+                .iter()
+                .any(|schedule_type| matches!(schedule_type, ScheduleType::Elevation(_)))
+        }) {
+            Err(ConfigError::WeekdayError)?
+        }
+        if !exceptions.is_empty()
+            && [day_type, night_type]
+                .iter()
+                .chain(&exception_types)
+                .any(|schedule_type| matches!(schedule_type, ScheduleType::Elevation(_)))
+        {
+            Err(ConfigError::ExceptionError)?
+        }
+
+        match (&day_type, &night_type) {
+            (ScheduleType::Elevation(_), ScheduleType::Elevation(_)) if !periods.is_empty() => Err(
+                ConfigError::PeriodError(periods[0].name.clone(), "cannot be used with elevations"),
+            )?,
+            (ScheduleType::Elevation(day), ScheduleType::Elevation(night)) if day > night => (),
+            (ScheduleType::Elevation(_), _) | (_, ScheduleType::Elevation(_)) => {
+                Err(ConfigError::ElevationError)?
+            }
+            _ => (),
+        }
+
         Ok(Config {
             day: day_color,
             night: night_color,
+            periods,
+            outputs,
             location: self.location,
             schedule: Schedule {
                 day: day_type,
                 night: night_type,
+                periods: period_types,
+                transition,
+                polar_fallback,
+                zone,
+                weekdays,
+                exceptions,
             },
         })
     }
 }
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq)]
 #[cfg_attr(test, derive(Debug))]
 pub enum ScheduleType {
     Auto,
     Fixed(NaiveTime),
     Relative(TimeDelta),
+    /// Solar event shifted by an offset
+    Solar(SolarEvent, TimeDelta),
+    /// Sun elevation in degrees, only valid when both day and night use it
+    Elevation(f64),
 }
 
 impl ScheduleType {
@@ -223,20 +864,202 @@ impl ScheduleType {
     }
 }
 
+/// Day and night starts on one day of the week
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct WeekdaySchedule {
+    pub day: ScheduleType,
+    pub night: ScheduleType,
+    /// Config table `day` is set in, like `schedule.sat` or `schedule.weekend`
+    pub day_table: &'static str,
+    /// Config table `night` is set in
+    pub night_table: &'static str,
+}
+
+/// Day and night starts replacing the usual ones on some dates
+#[derive(Clone, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub struct Exception {
+    /// Inclusive date ranges the exception applies to
+    pub dates: Vec<(NaiveDate, NaiveDate)>,
+    /// Replaced start of day, the usual one when `None`
+    pub day: Option<ScheduleType>,
+    /// Replaced start of night, the usual one when `None`
+    pub night: Option<ScheduleType>,
+}
+
+impl Exception {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.dates
+            .iter()
+            .any(|(from, to)| (*from..=*to).contains(&date))
+    }
+}
+
+/// What to follow on dates where a solar event of the schedule does not occur
+#[derive(Clone, Copy, PartialEq)]
+#[cfg_attr(test, derive(Debug))]
+pub enum PolarFallback {
+    /// Day during polar day, night during polar night
+    Sun,
+    Day,
+    Night,
+    /// Fixed day and night times
+    Fixed(NaiveTime, NaiveTime),
+}
+
+#[derive(Clone)]
 #[cfg_attr(test, derive(Debug))]
 pub struct Schedule {
     pub day: ScheduleType,
     pub night: ScheduleType,
+    /// Starts of the extra periods, in the same order as [`Config::periods`]
+    pub periods: Vec<ScheduleType>,
+    pub transition: TimeDelta,
+    pub polar_fallback: PolarFallback,
+    /// Zone the dates and fixed times are interpreted in
+    pub zone: Zone,
+    /// Overrides of `day` and `night` from Monday to Sunday
+    pub weekdays: [Option<WeekdaySchedule>; 7],
+    /// Overrides of `day` and `night` on dates, the first one containing a date is used
+    pub exceptions: Vec<Exception>,
+}
+
+impl Schedule {
+    /// Returns the day and night starts on `weekday`, ignoring exceptions
+    pub fn weekday_day_night(&self, weekday: Weekday) -> (ScheduleType, ScheduleType) {
+        self.weekdays[weekday.num_days_from_monday() as usize]
+            .map_or((self.day, self.night), |weekday| {
+                (weekday.day, weekday.night)
+            })
+    }
+
+    /// Returns the config keys of the day and night starts on `date`
+    pub fn day_night_keys(&self, date: NaiveDate) -> (String, String) {
+        let (day_table, night_table) = self.weekdays
+            [date.weekday().num_days_from_monday() as usize]
+            .map_or(("schedule", "schedule"), |weekday| {
+                (weekday.day_table, weekday.night_table)
+            });
+        let exception_key = |field: fn(&Exception) -> Option<ScheduleType>, name: &str| {
+            self.exceptions
+                .iter()
+                .position(|exception| exception.contains(date) && field(exception).is_some())
+                .map(|index| format!("schedule.exception[{}].{}", index, name))
+        };
+        (
+            exception_key(|exception| exception.day, "day")
+                .unwrap_or_else(|| format!("{}.day", day_table)),
+            exception_key(|exception| exception.night, "night")
+                .unwrap_or_else(|| format!("{}.night", night_table)),
+        )
+    }
+
+    /// Returns the day and night starts on `date`
+    pub fn day_night(&self, date: NaiveDate) -> (ScheduleType, ScheduleType) {
+        let (day, night) = self.weekday_day_night(date.weekday());
+        let exceptions = || {
+            self.exceptions
+                .iter()
+                .filter(|exception| exception.contains(date))
+        };
+        (
+            exceptions()
+                .find_map(|exception| exception.day)
+                .unwrap_or(day),
+            exceptions()
+                .find_map(|exception| exception.night)
+                .unwrap_or(night),
+        )
+    }
+}
+
+#[cfg_attr(test, derive(Debug))]
+pub struct Period {
+    pub name: String,
+    pub color: Color,
+}
+
+#[cfg_attr(test, derive(Debug))]
+pub struct Output {
+    /// Output name, `make model` or description, may contain `*` and `?` wildcards
+    pub pattern: String,
+    /// Colors for `[day, night, periods...]`, `None` when the output is left untouched
+    pub colors: Option<Vec<Color>>,
+    pub lut: Option<Arc<Lut>>,
 }
 
 #[cfg_attr(test, derive(Debug))]
 pub struct Config {
     pub day: Color,
     pub night: Color,
+    pub periods: Vec<Period>,
+    pub outputs: Vec<Output>,
     pub location: Option<Location>,
     pub schedule: Schedule,
 }
 
+impl Config {
+    pub fn color(&self, mode: ColorMode) -> Color {
+        match mode {
+            ColorMode::Day => self.day,
+            ColorMode::Night => self.night,
+            ColorMode::Period(index) => self.periods[index].color,
+        }
+    }
+
+    /// Blends the colors of every output from `previous_mode` to `mode`
+    pub fn output_colors(
+        &self,
+        previous_mode: ColorMode,
+        mode: ColorMode,
+        progress: f64,
+    ) -> OutputColors {
+        OutputColors {
+            default: self
+                .color(previous_mode)
+                .interpolate(self.color(mode), progress),
+            outputs: self
+                .outputs
+                .iter()
+                .map(|output| {
+                    let color = output.colors.as_ref().map(|colors| {
+                        colors[previous_mode.index()].interpolate(colors[mode.index()], progress)
+                    });
+                    (output.pattern.clone(), color)
+                })
+                .collect(),
+            luts: self
+                .outputs
+                .iter()
+                .filter_map(|output| Some((output.pattern.clone(), output.lut.clone()?)))
+                .collect(),
+            selected: Vec::new(),
+        }
+    }
+
+    /// Finds the mode `name` refers to, the reverse of `mode_name`
+    pub fn mode_by_name(&self, name: &str) -> Option<ColorMode> {
+        match name {
+            "day" => Some(ColorMode::Day),
+            "night" => Some(ColorMode::Night),
+            _ => self
+                .periods
+                .iter()
+                .position(|period| period.name == name)
+                .map(ColorMode::Period),
+        }
+    }
+
+    pub fn mode_name(&self, mode: ColorMode) -> &str {
+        match mode {
+            ColorMode::Day => "day",
+            ColorMode::Night => "night",
+            ColorMode::Period(index) => &self.periods[index].name,
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use core::panic;
@@ -302,12 +1125,106 @@ mod test {
             config.night,
             Color {
                 brightness: 0.5,
-                gamma: 0.4,
+                gamma: [0.4; 3],
                 ..Color::default()
             }
         );
     }
 
+    #[test]
+    fn gamma_channels() {
+        let file = "
+                [day]
+                gamma = [1.0, 0.95, 0.9]
+
+                [night]
+                gamma = 0.8
+                gamma-blue = 0.7
+
+                [location]
+                latitude = 0
+                longitude = 0
+            ";
+        let config = RawConfig::read(file).unwrap().check().unwrap();
+        assert_eq!(config.day.gamma, [1.0, 0.95, 0.9]);
+        assert_eq!(config.night.gamma, [0.8, 0.8, 0.7]);
+
+        assert!(RawConfig::read("[day]\ngamma = [1.0, 0.9]").is_err());
+        let result = RawConfig::read("[day]\ngamma = [1.0, -0.9, 1]")
+            .unwrap()
+            .check();
+        assert!(matches!(
+            &result,
+            Err(err) if matches!(
+                err.downcast_ref::<ConfigError>(),
+                Some(ConfigError::ValidationError(ValidationErrors(map)))
+                    if map.contains_key("day")
+            )
+        ));
+        assert!(result.unwrap_err().to_string().contains("day.gamma"));
+
+        // A gamma of 0 flattens the whole ramp
+        for (gamma, field) in [
+            ("gamma = 0", "`day.gamma`"),
+            ("gamma-red = 0", "`day.gamma_red`"),
+        ] {
+            let error = RawConfig::read(&format!("[day]\n{}", gamma))
+                .unwrap()
+                .check()
+                .unwrap_err()
+                .to_string();
+            assert!(
+                error.contains(field) && error.contains("greater than 0"),
+                "{}",
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn color_channels() {
+        let file = "
+                [day]
+                green = 0.9
+                blue-offset = 0.05
+
+                [night]
+                red = 1.2
+                red-offset = -0.1
+
+                [location]
+                latitude = 0
+                longitude = 0
+            ";
+        let config = RawConfig::read(file).unwrap().check().unwrap();
+        assert_eq!(config.day.gain, [1.0, 0.9, 1.0]);
+        assert_eq!(config.day.offset, [0.0, 0.0, 0.05]);
+        assert_eq!(config.night.gain, [1.2, 1.0, 1.0]);
+        assert_eq!(config.night.offset, [-0.1, 0.0, 0.0]);
+
+        for channel in ["red = -0.5", "green-offset = 1.5"] {
+            let file = format!(
+                "
+                [day]
+                {}
+
+                [location]
+                latitude = 0
+                longitude = 0
+                ",
+                channel
+            );
+            assert!(matches!(
+                RawConfig::read(&file).unwrap().check(),
+                Err(err) if matches!(
+                    err.downcast_ref::<ConfigError>(),
+                    Some(ConfigError::ValidationError(ValidationErrors(map)))
+                        if map.contains_key("day")
+                )
+            ));
+        }
+    }
+
     mod location {
         use super::*;
 
@@ -529,6 +1446,1089 @@ mod test {
         }
     }
 
+    mod elevation {
+        use super::*;
+
+        #[test]
+        fn day_night_elevation() {
+            let file = "
+                [location]
+                latitude = 0
+                longitude = 0
+
+                [schedule]
+                day = \"elevation:3\"
+                night = \"elevation:-6\"
+            ";
+            let config = RawConfig::read(file).unwrap().check().unwrap();
+            assert_eq!(config.schedule.day, ScheduleType::Elevation(3.0));
+            assert_eq!(config.schedule.night, ScheduleType::Elevation(-6.0));
+        }
+
+        #[test]
+        fn mixed() {
+            let file = "
+                [location]
+                latitude = 0
+                longitude = 0
+
+                [schedule]
+                day = \"elevation:3\"
+                night = \"20:00\"
+            ";
+            assert_same_error(
+                RawConfig::read(file).unwrap().check(),
+                ConfigError::ElevationError,
+            );
+        }
+
+        #[test]
+        fn day_below_night() {
+            let file = "
+                [location]
+                latitude = 0
+                longitude = 0
+
+                [schedule]
+                day = \"elevation:-6\"
+                night = \"elevation:3\"
+            ";
+            assert_same_error(
+                RawConfig::read(file).unwrap().check(),
+                ConfigError::ElevationError,
+            );
+        }
+
+        #[test]
+        fn location_required() {
+            let file = "
+                [schedule]
+                day = \"elevation:3\"
+                night = \"elevation:-6\"
+            ";
+            assert_same_error(
+                RawConfig::read(file).unwrap().check(),
+                ConfigError::LocationError,
+            );
+        }
+
+        #[test]
+        fn out_of_range() {
+            let file = "
+                [location]
+                latitude = 0
+                longitude = 0
+
+                [schedule]
+                day = \"elevation:91\"
+                night = \"elevation:foo\"
+            ";
+            assert!(matches!(
+                RawConfig::read(file).unwrap().check(),
+                Err(err) if matches!(
+                    err.downcast_ref::<ConfigError>(),
+                    Some(ConfigError::ValidationError(ValidationErrors(map)))
+                        if matches!(
+                         map.get("schedule"),
+                         Some(ValidationErrorsKind::Struct(errs))
+                          if errs.errors().contains_key("day") && errs.errors().contains_key("night")
+                        )
+                )
+            ));
+        }
+    }
+
+    mod period {
+        use super::*;
+
+        #[test]
+        fn periods() {
+            let file = "
+                [location]
+                latitude = 0
+                longitude = 0
+
+                [schedule]
+                night = \"00:30\"
+
+                [[period]]
+                name = \"evening\"
+                start = \"sunset\"
+                temperature = 4500
+
+                [[period]]
+                name = \"late-night\"
+                start = \"23:00\"
+                temperature = 2700
+                brightness = 0.7
+            ";
+            let config = RawConfig::read(file).unwrap().check().unwrap();
+            assert_eq!(config.periods.len(), 2);
+            assert_eq!(config.mode_name(ColorMode::Period(0)), "evening");
+            assert_eq!(
+                config.color(ColorMode::Period(0)),
+                Color {
+                    temperature: 4500,
+                    ..Color::default()
+                }
+            );
+            assert_eq!(config.mode_name(ColorMode::Period(1)), "late-night");
+            assert_eq!(
+                config.mode_by_name("late-night"),
+                Some(ColorMode::Period(1))
+            );
+            assert_eq!(config.mode_by_name("night"), Some(ColorMode::Night));
+            assert_eq!(config.mode_by_name("morning"), None);
+            assert_eq!(
+                config.color(ColorMode::Period(1)),
+                Color {
+                    temperature: 2700,
+                    brightness: 0.7,
+                    ..Color::default()
+                }
+            );
+            assert_eq!(
+                config.schedule.periods,
+                vec![
+                    ScheduleType::Solar(SolarEvent::Sunset, TimeDelta::zero()),
+                    ScheduleType::Fixed(NaiveTime::from_hms_opt(23, 0, 0).unwrap())
+                ]
+            );
+        }
+
+        #[test]
+        fn relative_start() {
+            let file = "
+                [location]
+                latitude = 0
+                longitude = 0
+
+                [schedule]
+                night = \"00:30\"
+
+                [[period]]
+                name = \"evening\"
+                start = \"+01:00\"
+            ";
+            let config = RawConfig::read(file).unwrap().check().unwrap();
+            assert_eq!(
+                config.schedule.periods,
+                vec![ScheduleType::Relative(TimeDelta::hours(1))]
+            );
+        }
+
+        #[test]
+        fn location_required() {
+            let file = "
+                [schedule]
+                day = \"08:00\"
+                night = \"23:00\"
+
+                [[period]]
+                name = \"evening\"
+                start = \"sunset\"
+            ";
+            assert_same_error(
+                RawConfig::read(file).unwrap().check(),
+                ConfigError::LocationError,
+            );
+        }
+
+        #[test]
+        fn invalid_periods() {
+            for (name, start, reason) in [
+                (
+                    "night",
+                    "20:00",
+                    "cannot be named `day`, `night`, `enabled` or `lut`",
+                ),
+                (
+                    "lut",
+                    "20:00",
+                    "cannot be named `day`, `night`, `enabled` or `lut`",
+                ),
+                (
+                    "evening",
+                    "elevation:3",
+                    "must start at 'HH:MM', '+HH:MM', '-HH:MM' or a solar anchor",
+                ),
+            ] {
+                let file = format!(
+                    "
+                    [schedule]
+                    day = \"08:00\"
+                    night = \"23:00\"
+
+                    [[period]]
+                    name = \"{}\"
+                    start = \"{}\"
+                    ",
+                    name, start
+                );
+                assert_same_error(
+                    RawConfig::read(&file).unwrap().check(),
+                    ConfigError::PeriodError(name.to_string(), reason),
+                );
+            }
+        }
+
+        #[test]
+        fn duplicate_name() {
+            let file = "
+                [schedule]
+                day = \"08:00\"
+                night = \"23:00\"
+
+                [[period]]
+                name = \"evening\"
+                start = \"19:00\"
+
+                [[period]]
+                name = \"evening\"
+                start = \"21:00\"
+            ";
+            assert_same_error(
+                RawConfig::read(file).unwrap().check(),
+                ConfigError::PeriodError(
+                    "evening".to_string(),
+                    "has the name of another [[period]]",
+                ),
+            );
+        }
+
+        #[test]
+        fn with_elevation() {
+            let file = "
+                [location]
+                latitude = 0
+                longitude = 0
+
+                [schedule]
+                day = \"elevation:3\"
+                night = \"elevation:-6\"
+
+                [[period]]
+                name = \"evening\"
+                start = \"21:00\"
+            ";
+            assert_same_error(
+                RawConfig::read(file).unwrap().check(),
+                ConfigError::PeriodError("evening".to_string(), "cannot be used with elevations"),
+            );
+        }
+
+        #[test]
+        fn invalid_color() {
+            let file = "
+                [schedule]
+                day = \"08:00\"
+                night = \"23:00\"
+
+                [[period]]
+                name = \"evening\"
+                start = \"19:00\"
+                temperature = 100
+            ";
+            assert!(matches!(
+                RawConfig::read(file).unwrap().check(),
+                Err(err) if matches!(
+                    err.downcast_ref::<ConfigError>(),
+                    Some(ConfigError::ValidationError(ValidationErrors(map)))
+                        if matches!(map.get("period"), Some(ValidationErrorsKind::List(_)))
+                )
+            ));
+        }
+    }
+
+    mod output {
+        use super::*;
+        use crate::output::OutputInfo;
+
+        fn named(name: &str) -> OutputInfo {
+            OutputInfo {
+                name: Some(name.to_string()),
+                ..OutputInfo::default()
+            }
+        }
+
+        const FILE: &str = "
+            [night]
+            temperature = 4000
+            brightness = 0.8
+
+            [schedule]
+            day = \"08:00\"
+            night = \"20:00\"
+
+            [[period]]
+            name = \"evening\"
+            start = \"18:00\"
+            temperature = 5000
+
+            [output.\"eDP-1\".night]
+            temperature = 3000
+
+            [output.\"HDMI-*\"]
+            enabled = false
+        ";
+
+        #[test]
+        fn overrides() {
+            let config = RawConfig::read(FILE).unwrap().check().unwrap();
+            assert_eq!(config.outputs.len(), 2);
+
+            let colors = config.output_colors(ColorMode::Day, ColorMode::Night, 1.0);
+            assert_eq!(
+                colors.get(&named("eDP-1")),
+                Some(Color {
+                    temperature: 3000,
+                    brightness: 0.8,
+                    ..Color::default()
+                })
+            );
+            assert_eq!(colors.get(&named("HDMI-A-1")), None);
+            assert_eq!(colors.get(&named("DP-1")), Some(config.night));
+
+            let colors = config.output_colors(ColorMode::Day, ColorMode::Period(0), 1.0);
+            assert_eq!(colors.get(&named("eDP-1")), Some(config.periods[0].color));
+        }
+
+        #[test]
+        fn file_order() {
+            let file = "
+                [schedule]
+                day = \"08:00\"
+                night = \"20:00\"
+
+                [output.\"eDP-*\".night]
+                temperature = 3000
+
+                [output.\"*\"]
+                enabled = false
+
+                [output.\"HDMI-?-1\"]
+                enabled = true
+            ";
+            let config = RawConfig::read(file).unwrap().check().unwrap();
+            let patterns: Vec<&str> = config.outputs.iter().map(|o| o.pattern.as_str()).collect();
+            assert_eq!(patterns, ["eDP-*", "*", "HDMI-?-1"]);
+
+            let colors = config.output_colors(ColorMode::Night, ColorMode::Night, 1.0);
+            assert_eq!(
+                colors.get(&named("eDP-1")),
+                Some(Color {
+                    temperature: 3000,
+                    ..Color::default()
+                })
+            );
+            assert_eq!(colors.get(&named("HDMI-A-1")), None);
+
+            let invalid = format!("{}\n[output.\"DP-1\".day]\ntemperature = 1", file);
+            assert!(matches!(
+                RawConfig::read(&invalid).unwrap().check(),
+                Err(err) if err.to_string().contains("field `output.\"DP-1\".day.temperature`")
+            ));
+        }
+
+        #[test]
+        fn period_override() {
+            let file = format!(
+                "{}
+                [output.\"eDP-1\".evening]
+                brightness = 0.5
+                ",
+                FILE
+            );
+            let config = RawConfig::read(&file).unwrap().check().unwrap();
+            let colors = config.output_colors(ColorMode::Day, ColorMode::Period(0), 1.0);
+            assert_eq!(
+                colors.get(&named("eDP-1")),
+                Some(Color {
+                    temperature: 5000,
+                    brightness: 0.5,
+                    ..Color::default()
+                })
+            );
+        }
+
+        #[test]
+        fn lut() {
+            let name = format!("wl-nightlight-{}.csv", std::process::id());
+            let path = std::env::temp_dir().join(&name);
+            std::fs::write(&path, "r,g,b\n0,0,0\n1,0.5,1\n").unwrap();
+            // Relative to the directory of the config file
+            let file = format!(
+                "{}
+                [output.\"DP-*\"]
+                lut = {:?}
+                ",
+                FILE, name
+            );
+            let config = RawConfig::read(&file)
+                .unwrap()
+                .relative_to(&std::env::temp_dir())
+                .check();
+            std::fs::remove_file(&path).unwrap();
+
+            let colors = config
+                .unwrap()
+                .output_colors(ColorMode::Day, ColorMode::Night, 1.0);
+            assert!(colors.lut(&named("DP-1")).is_some());
+            assert!(colors.lut(&named("eDP-1")).is_none());
+
+            assert!(
+                RawConfig::read(&file)
+                    .unwrap()
+                    .relative_to(&std::env::temp_dir())
+                    .check()
+                    .is_err()
+            );
+        }
+
+        #[test]
+        fn unknown_mode() {
+            let file = format!(
+                "{}
+                [output.\"DP-1\".late-night]
+                brightness = 0.5
+                ",
+                FILE
+            );
+            assert_same_error(
+                RawConfig::read(&file).unwrap().check(),
+                ConfigError::OutputError,
+            );
+        }
+    }
+
+    mod transition {
+        use super::*;
+
+        #[test]
+        fn default() {
+            let file = "
+                [schedule]
+                day = \"08:00\"
+                night = \"20:00\"
+            ";
+            let config = RawConfig::read(file).unwrap().check().unwrap();
+            assert_eq!(config.schedule.transition, TimeDelta::zero());
+        }
+
+        #[test]
+        fn duration() {
+            let file = "
+                [schedule]
+                day = \"08:00\"
+                night = \"20:00\"
+                transition = \"1h30m15s\"
+            ";
+            let config = RawConfig::read(file).unwrap().check().unwrap();
+            assert_eq!(
+                config.schedule.transition,
+                TimeDelta::hours(1) + TimeDelta::minutes(30) + TimeDelta::seconds(15)
+            );
+        }
+
+        #[test]
+        fn invalid_duration() {
+            for duration in ["", "45", "m", "45x", "-45m", "1h 30m"] {
+                let file = format!(
+                    "
+                    [schedule]
+                    day = \"08:00\"
+                    night = \"20:00\"
+                    transition = \"{}\"
+                    ",
+                    duration
+                );
+                assert!(matches!(
+                    RawConfig::read(&file).unwrap().check(),
+                    Err(err) if matches!(
+                        err.downcast_ref::<ConfigError>(),
+                        Some(ConfigError::ValidationError(ValidationErrors(map)))
+                            if matches!(
+                             map.get("schedule"),
+                             Some(ValidationErrorsKind::Struct(errs))
+                              if errs.errors().contains_key("transition")
+                            )
+                    )
+                ));
+            }
+        }
+    }
+
+    mod polar_fallback {
+        use super::*;
+
+        fn read_fallback(fallback: &str) -> anyhow::Result<Config> {
+            let file = format!(
+                "
+                [location]
+                latitude = 78.2
+                longitude = 15.6
+
+                [schedule]
+                polar-fallback = \"{}\"
+                ",
+                fallback
+            );
+            RawConfig::read(&file).unwrap().check()
+        }
+
+        #[test]
+        fn default() {
+            let file = "
+                [location]
+                latitude = 78.2
+                longitude = 15.6
+            ";
+            let config = RawConfig::read(file).unwrap().check().unwrap();
+            assert_eq!(config.schedule.polar_fallback, PolarFallback::Sun);
+        }
+
+        #[test]
+        fn fallbacks() {
+            assert_eq!(
+                read_fallback("night").unwrap().schedule.polar_fallback,
+                PolarFallback::Night
+            );
+            assert_eq!(
+                read_fallback("fixed:07:30-19:00")
+                    .unwrap()
+                    .schedule
+                    .polar_fallback,
+                PolarFallback::Fixed(
+                    NaiveTime::from_hms_opt(7, 30, 0).unwrap(),
+                    NaiveTime::from_hms_opt(19, 0, 0).unwrap()
+                )
+            );
+        }
+
+        #[test]
+        fn invalid_fallback() {
+            for fallback in [
+                "",
+                "dusk",
+                "fixed",
+                "fixed:07:30",
+                "fixed:7-19",
+                "fixed:07:30-",
+            ] {
+                assert!(matches!(
+                    read_fallback(fallback),
+                    Err(err) if matches!(
+                        err.downcast_ref::<ConfigError>(),
+                        Some(ConfigError::ValidationError(ValidationErrors(map)))
+                            if matches!(
+                             map.get("schedule"),
+                             Some(ValidationErrorsKind::Struct(errs))
+                              if errs.errors().contains_key("polar_fallback")
+                            )
+                    )
+                ));
+            }
+        }
+    }
+
+    mod weekdays {
+        use super::*;
+
+        fn fixed(hour: u32, min: u32) -> ScheduleType {
+            ScheduleType::Fixed(NaiveTime::from_hms_opt(hour, min, 0).unwrap())
+        }
+
+        #[test]
+        fn overrides() {
+            let file = "
+                [schedule]
+                day = \"07:00\"
+                night = \"22:00\"
+
+                [schedule.weekend]
+                day = \"09:30\"
+                night = \"23:30\"
+
+                [schedule.sun]
+                night = \"21:00\"
+
+                [schedule.wed]
+                day = \"06:00\"
+            ";
+            let schedule = RawConfig::read(file).unwrap().check().unwrap().schedule;
+            assert_eq!(
+                schedule.weekday_day_night(Weekday::Mon),
+                (fixed(7, 0), fixed(22, 0))
+            );
+            assert_eq!(
+                schedule.weekday_day_night(Weekday::Wed),
+                (fixed(6, 0), fixed(22, 0))
+            );
+            assert_eq!(
+                schedule.weekday_day_night(Weekday::Sat),
+                (fixed(9, 30), fixed(23, 30))
+            );
+            assert_eq!(
+                schedule.weekday_day_night(Weekday::Sun),
+                (fixed(9, 30), fixed(21, 0))
+            );
+            assert_eq!(schedule.weekdays.iter().flatten().count(), 3);
+
+            // 2000-01-02 is a Sunday
+            assert_eq!(
+                schedule.day_night_keys(NaiveDate::from_ymd_opt(2000, 1, 2).unwrap()),
+                (
+                    "schedule.weekend.day".to_string(),
+                    "schedule.sun.night".to_string()
+                )
+            );
+            assert_eq!(
+                schedule.day_night_keys(NaiveDate::from_ymd_opt(2000, 1, 3).unwrap()),
+                ("schedule.day".to_string(), "schedule.night".to_string())
+            );
+        }
+
+        #[test]
+        fn location_required() {
+            let file = "
+                [schedule]
+                day = \"07:00\"
+                night = \"22:00\"
+
+                [schedule.fri]
+                night = \"sunset\"
+            ";
+            assert_same_error(
+                RawConfig::read(file).unwrap().check(),
+                ConfigError::LocationError,
+            );
+        }
+
+        #[test]
+        fn with_elevation() {
+            let file = "
+                [location]
+                latitude = 51.8
+                longitude = -0.1
+
+                [schedule]
+                day = \"elevation:3\"
+                night = \"elevation:-6\"
+
+                [schedule.weekend]
+                day = \"elevation:6\"
+            ";
+            assert_same_error(
+                RawConfig::read(file).unwrap().check(),
+                ConfigError::WeekdayError,
+            );
+        }
+
+        #[test]
+        fn invalid_time() {
+            let file = "
+                [schedule.mon]
+                day = \"7am\"
+            ";
+            assert!(matches!(
+                RawConfig::read(file).unwrap().check(),
+                Err(err) if matches!(
+                    err.downcast_ref::<ConfigError>(),
+                    Some(ConfigError::ValidationError(ValidationErrors(map)))
+                        if matches!(
+                         map.get("schedule"),
+                         Some(ValidationErrorsKind::Struct(errs))
+                          if errs.errors().contains_key("mon")
+                        )
+                )
+            ));
+        }
+    }
+
+    mod exception {
+        use super::*;
+
+        fn date(month: u32, day: u32) -> NaiveDate {
+            NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+        }
+
+        fn fixed(hour: u32, min: u32) -> ScheduleType {
+            ScheduleType::Fixed(NaiveTime::from_hms_opt(hour, min, 0).unwrap())
+        }
+
+        #[test]
+        fn date_range() {
+            let file = "
+                [schedule]
+                day = \"07:00\"
+                night = \"22:00\"
+
+                [schedule.sat]
+                day = \"09:00\"
+
+                [[schedule.exception]]
+                from = \"2026-12-24\"
+                to = \"2027-01-02\"
+                night = \"20:00\"
+
+                [[schedule.exception]]
+                from = \"2026-12-26\"
+                day = \"10:00\"
+                night = \"23:00\"
+            ";
+            let schedule = RawConfig::read(file).unwrap().check().unwrap().schedule;
+            assert_eq!(
+                schedule.day_night(date(12, 23)),
+                (fixed(7, 0), fixed(22, 0))
+            );
+            assert_eq!(
+                schedule.day_night(date(12, 24)),
+                (fixed(7, 0), fixed(20, 0))
+            );
+            // The first exception takes the night, the second one the day
+            assert_eq!(
+                schedule.day_night(date(12, 26)),
+                (fixed(10, 0), fixed(20, 0))
+            );
+            // Saturday
+            assert_eq!(
+                schedule.day_night(date(12, 19)),
+                (fixed(9, 0), fixed(22, 0))
+            );
+            assert_eq!(
+                schedule.day_night(NaiveDate::from_ymd_opt(2027, 1, 2).unwrap()),
+                (fixed(9, 0), fixed(20, 0))
+            );
+            assert_eq!(
+                schedule.day_night_keys(NaiveDate::from_ymd_opt(2027, 1, 2).unwrap()),
+                (
+                    "schedule.sat.day".to_string(),
+                    "schedule.exception[0].night".to_string()
+                )
+            );
+            assert_eq!(
+                schedule.day_night_keys(date(12, 26)),
+                (
+                    "schedule.exception[1].day".to_string(),
+                    "schedule.exception[0].night".to_string()
+                )
+            );
+        }
+
+        #[test]
+        fn dates_file() {
+            let name = format!("wl-nightlight-holidays-{}", std::process::id());
+            let path = std::env::temp_dir().join(&name);
+            std::fs::write(&path, "# Holidays\n2026-05-01\n\n2026-12-25 # Christmas\n").unwrap();
+            // Relative to the directory of the config file
+            let file = format!(
+                "
+                [schedule]
+                day = \"07:00\"
+                night = \"22:00\"
+
+                [[schedule.exception]]
+                dates-file = {:?}
+                day = \"09:00\"
+                ",
+                name
+            );
+            let result = RawConfig::read(&file)
+                .unwrap()
+                .relative_to(&std::env::temp_dir())
+                .check();
+            std::fs::remove_file(&path).unwrap();
+
+            let schedule = result.unwrap().schedule;
+            assert_eq!(
+                schedule.exceptions[0].dates,
+                vec![(date(5, 1), date(5, 1)), (date(12, 25), date(12, 25))]
+            );
+            assert_eq!(
+                schedule.day_night(date(12, 25)),
+                (fixed(9, 0), fixed(22, 0))
+            );
+            assert_eq!(
+                schedule.day_night(date(12, 26)),
+                (fixed(7, 0), fixed(22, 0))
+            );
+        }
+
+        #[test]
+        fn invalid_dates_file() {
+            let path = std::env::temp_dir().join(format!(
+                "wl-nightlight-invalid-holidays-{}",
+                std::process::id()
+            ));
+            std::fs::write(&path, "2026-05-01\n1st of May\n").unwrap();
+            let file = format!(
+                "
+                [[schedule.exception]]
+                dates-file = {:?}
+                day = \"09:00\"
+                ",
+                path
+            );
+            let result = RawConfig::read(&file).unwrap().check();
+            std::fs::remove_file(&path).unwrap();
+
+            assert!(result.unwrap_err().to_string().contains("line 2"));
+        }
+
+        #[test]
+        fn invalid_exceptions() {
+            for exception in [
+                "day = \"09:00\"",
+                "to = \"2026-12-24\"",
+                "from = \"2026-12-24\"\nto = \"2026-12-23\"",
+                "from = \"2026-12-24\"\nday = \"elevation:3\"",
+            ] {
+                let file = format!(
+                    "
+                    [location]
+                    latitude = 51.8
+                    longitude = -0.1
+
+                    [[schedule.exception]]
+                    {}
+                    ",
+                    exception
+                );
+                assert_same_error(
+                    RawConfig::read(&file).unwrap().check(),
+                    ConfigError::ExceptionError,
+                );
+            }
+
+            let file = "
+                [[schedule.exception]]
+                from = \"24.12.2026\"
+            ";
+            assert!(matches!(
+                RawConfig::read(file).unwrap().check(),
+                Err(err) if matches!(
+                    err.downcast_ref::<ConfigError>(),
+                    Some(ConfigError::ValidationError(ValidationErrors(map)))
+                        if matches!(
+                         map.get("schedule"),
+                         Some(ValidationErrorsKind::Struct(errs))
+                          if errs.errors().contains_key("exception")
+                        )
+                )
+            ));
+        }
+    }
+
+    mod timezone {
+        use super::*;
+
+        fn read_timezone(timezone: &str) -> anyhow::Result<Config> {
+            let file = format!(
+                "
+                timezone = \"{}\"
+
+                [schedule]
+                day = \"07:00\"
+                night = \"19:00\"
+                ",
+                timezone
+            );
+            RawConfig::read(&file).unwrap().check()
+        }
+
+        #[test]
+        fn default() {
+            let file = "
+                [schedule]
+                day = \"07:00\"
+                night = \"19:00\"
+            ";
+            let config = RawConfig::read(file).unwrap().check().unwrap();
+            assert_eq!(config.schedule.zone, Zone::Local);
+        }
+
+        #[test]
+        fn named() {
+            assert_eq!(
+                read_timezone("Europe/London").unwrap().schedule.zone,
+                Zone::named("Europe/London").unwrap()
+            );
+        }
+
+        #[test]
+        fn unknown() {
+            for timezone in ["", "Europe/Nowhere"] {
+                assert!(matches!(
+                    read_timezone(timezone),
+                    Err(err) if matches!(
+                        err.downcast_ref::<ConfigError>(),
+                        Some(ConfigError::ValidationError(ValidationErrors(map)))
+                            if map.contains_key("timezone")
+                    )
+                ));
+            }
+        }
+    }
+
+    mod white_point_model {
+        use super::*;
+
+        fn read_white_point_model(model: &str) -> anyhow::Result<Config> {
+            let file = format!(
+                "
+                white-point-model = \"{}\"
+
+                [schedule]
+                day = \"07:00\"
+                night = \"20:00\"
+
+                [night]
+                temperature = 3000
+
+                [[period]]
+                name = \"evening\"
+                start = \"18:00\"
+
+                [output.\"DP-1\".day]
+                brightness = 0.8
+                ",
+                model
+            );
+            RawConfig::read(&file).unwrap().check()
+        }
+
+        #[test]
+        fn default() {
+            let file = "
+                [schedule]
+                day = \"07:00\"
+                night = \"19:00\"
+            ";
+            let config = RawConfig::read(file).unwrap().check().unwrap();
+            assert_eq!(config.day.white_point_model, WhitePointModel::Blackbody);
+        }
+
+        #[test]
+        fn daylight() {
+            let config = read_white_point_model("daylight").unwrap();
+            let colors = config.output_colors(ColorMode::Day, ColorMode::Day, 1.0);
+            for color in [config.day, config.night, config.periods[0].color]
+                .into_iter()
+                .chain(colors.outputs.iter().filter_map(|(_, color)| *color))
+            {
+                assert_eq!(color.white_point_model, WhitePointModel::Daylight);
+            }
+            assert_eq!(
+                read_white_point_model("blackbody")
+                    .unwrap()
+                    .day
+                    .white_point_model,
+                WhitePointModel::Blackbody
+            );
+        }
+
+        #[test]
+        fn unknown() {
+            assert!(matches!(
+                read_white_point_model("cie"),
+                Err(err) if matches!(
+                    err.downcast_ref::<ConfigError>(),
+                    Some(ConfigError::ValidationError(ValidationErrors(map)))
+                        if map.contains_key("white_point_model")
+                )
+            ));
+        }
+    }
+
+    mod anchor {
+        use super::*;
+
+        #[test]
+        fn twilight() {
+            let file = "
+                [location]
+                latitude = 0
+                longitude = 0
+
+                [schedule]
+                day = \"civil-dawn\"
+                night = \"astronomical-dusk+00:30\"
+            ";
+            let config = RawConfig::read(file).unwrap().check().unwrap();
+            assert_eq!(
+                config.schedule.day,
+                ScheduleType::Solar(SolarEvent::Dawn(DawnType::Civil), TimeDelta::zero())
+            );
+            assert_eq!(
+                config.schedule.night,
+                ScheduleType::Solar(
+                    SolarEvent::Dusk(DawnType::Astronomical),
+                    TimeDelta::minutes(30)
+                )
+            );
+        }
+
+        #[test]
+        fn sunrise_sunset() {
+            let file = "
+                [location]
+                latitude = 0
+                longitude = 0
+
+                [schedule]
+                day = \"sunrise-01:15\"
+                night = \"sunset\"
+            ";
+            let config = RawConfig::read(file).unwrap().check().unwrap();
+            assert_eq!(
+                config.schedule.day,
+                ScheduleType::Solar(
+                    SolarEvent::Sunrise,
+                    -(TimeDelta::hours(1) + TimeDelta::minutes(15))
+                )
+            );
+            assert_eq!(
+                config.schedule.night,
+                ScheduleType::Solar(SolarEvent::Sunset, TimeDelta::zero())
+            );
+        }
+
+        #[test]
+        fn location_required() {
+            let file = "
+                [schedule]
+                day = \"08:00\"
+                night = \"nautical-dusk\"
+            ";
+            assert_same_error(
+                RawConfig::read(file).unwrap().check(),
+                ConfigError::LocationError,
+            );
+        }
+
+        #[test]
+        fn invalid_offset() {
+            let file = "
+                [schedule]
+                day = \"civil-dawn+1\"
+                night = \"civil-dusk 00:30\"
+            ";
+
+            assert!(matches!(
+                RawConfig::read(file).unwrap().check(),
+                Err(err) if matches!(
+                    err.downcast_ref::<ConfigError>(),
+                    Some(ConfigError::ValidationError(ValidationErrors(map)))
+                        if matches!(
+                         map.get("schedule"),
+                         Some(ValidationErrorsKind::Struct(errs))
+                          if errs.errors().contains_key("day") && errs.errors().contains_key("night")
+                        )
+                )
+            ));
+        }
+    }
+
     mod parse_time {
         use super::*;
 
@@ -0,0 +1,390 @@
+use std::{
+    io::{Read, Write},
+    os::{
+        fd::{AsFd, BorrowedFd},
+        unix::net::{UnixListener, UnixStream},
+    },
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use chrono::TimeDelta;
+use thiserror::Error;
+
+use crate::config::parse_duration;
+
+const SOCKET_NAME: &str = "wl-nightlight.sock";
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+/// Longest request line accepted from a client
+const REQUEST_MAX_LEN: usize = 4096;
+
+/// Returns `$XDG_RUNTIME_DIR/wl-nightlight.sock`
+pub fn socket_path() -> anyhow::Result<PathBuf> {
+    dirs::runtime_dir()
+        .map(|dir| dir.join(SOCKET_NAME))
+        .ok_or_else(|| {
+            anyhow::anyhow!("Unable to locate runtime directory, is XDG_RUNTIME_DIR set?")
+        })
+}
+
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub enum IpcRequest {
+    Status,
+    Toggle,
+    Set {
+        temperature: u16,
+    },
+    /// Forces a mode for `duration`, or until the next scheduled switch if `None`
+    Override {
+        mode: String,
+        duration: Option<TimeDelta>,
+    },
+    Resume,
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid request: {0:?}")]
+pub struct ParseRequestError(String);
+
+impl FromStr for IpcRequest {
+    type Err = ParseRequestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        match words.as_slice() {
+            ["status"] => Ok(IpcRequest::Status),
+            ["toggle"] => Ok(IpcRequest::Toggle),
+            ["set", "temperature", temperature] => temperature
+                .parse()
+                .map(|temperature| IpcRequest::Set { temperature })
+                .map_err(|_| ParseRequestError(s.to_string())),
+            ["override", duration, mode @ ..] if !mode.is_empty() => {
+                let duration = match *duration {
+                    "next" => None,
+                    duration => Some(
+                        parse_duration(duration).map_err(|_| ParseRequestError(s.to_string()))?,
+                    ),
+                };
+                Ok(IpcRequest::Override {
+                    mode: mode.join(" "),
+                    duration,
+                })
+            }
+            ["resume"] => Ok(IpcRequest::Resume),
+            _ => Err(ParseRequestError(s.to_string())),
+        }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl std::fmt::Display for IpcRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IpcRequest::Status => write!(f, "status"),
+            IpcRequest::Toggle => write!(f, "toggle"),
+            IpcRequest::Set { temperature } => write!(f, "set temperature {}", temperature),
+            IpcRequest::Override { mode, duration } => match duration {
+                Some(duration) => write!(f, "override {}s {}", duration.num_seconds(), mode),
+                None => write!(f, "override next {}", mode),
+            },
+            IpcRequest::Resume => write!(f, "resume"),
+        }
+    }
+}
+
+/// Sends a request to the running daemon and returns its reply
+pub fn send_request(path: &Path, request: IpcRequest) -> anyhow::Result<String> {
+    let mut stream = UnixStream::connect(path).map_err(|error| {
+        anyhow::anyhow!(
+            "Fail to connect to {:?}, is the daemon running? {}",
+            path,
+            error
+        )
+    })?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    writeln!(stream, "{}", request)?;
+
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    match reply.split_once('\n') {
+        Some(("ok", body)) => Ok(body.to_string()),
+        Some(("error", message)) => Err(anyhow::anyhow!("{}", message.trim_end())),
+        _ => Err(anyhow::anyhow!("Invalid reply from daemon: {:?}", reply)),
+    }
+}
+
+/// Client connection whose request line has not fully arrived yet
+struct PendingConnection {
+    stream: UnixStream,
+    request: Vec<u8>,
+    since: Instant,
+}
+
+impl PendingConnection {
+    /// Reads what the client has sent so far, returning the request line once it is complete
+    fn read_line(&mut self) -> std::io::Result<Option<String>> {
+        let mut buf = [0; 256];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) if self.request.is_empty() => {
+                    return Err(std::io::ErrorKind::UnexpectedEof.into());
+                }
+                Ok(0) => return Ok(Some(String::from_utf8_lossy(&self.request).into_owned())),
+                Ok(n) => self.request.extend_from_slice(&buf[..n]),
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => return Ok(None),
+                Err(err) => return Err(err),
+            }
+            if let Some(end) = self.request.iter().position(|&b| b == b'\n') {
+                return Ok(Some(
+                    String::from_utf8_lossy(&self.request[..end]).into_owned(),
+                ));
+            }
+            if self.request.len() > REQUEST_MAX_LEN {
+                return Err(std::io::ErrorKind::InvalidData.into());
+            }
+        }
+    }
+}
+
+/// Listening socket of the daemon, removed again when dropped
+pub struct IpcServer {
+    listener: UnixListener,
+    path: PathBuf,
+    pending: Vec<PendingConnection>,
+}
+
+impl IpcServer {
+    pub fn new(path: PathBuf) -> anyhow::Result<Self> {
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                anyhow::bail!("Another instance is already listening on {:?}", path);
+            }
+            // Left behind by an instance that did not exit cleanly
+            std::fs::remove_file(&path)?;
+        }
+        let listener = UnixListener::bind(&path)
+            .map_err(|error| anyhow::anyhow!("Fail to bind {:?}, {}", path, error))?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            path,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Returns the next complete request, or `None` once there are none left
+    ///
+    /// Never blocks: clients whose request has not fully arrived are kept for later calls, and
+    /// dropped once they take longer than a second. Malformed requests are answered with an
+    /// error and skipped.
+    pub fn accept(&mut self) -> std::io::Result<Option<IpcConnection>> {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    self.pending.push(PendingConnection {
+                        stream,
+                        request: Vec::new(),
+                        since: Instant::now(),
+                    });
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err),
+            }
+        }
+
+        let mut index = 0;
+        while index < self.pending.len() {
+            let line = match self.pending[index].read_line() {
+                Ok(Some(line)) => line,
+                Ok(None) if self.pending[index].since.elapsed() < CLIENT_TIMEOUT => {
+                    index += 1;
+                    continue;
+                }
+                Ok(None) => {
+                    log::warn!("Drop IPC client that did not send a request in time");
+                    self.pending.swap_remove(index);
+                    continue;
+                }
+                Err(error) => {
+                    log::warn!("Fail to read IPC request, {}", error);
+                    self.pending.swap_remove(index);
+                    continue;
+                }
+            };
+
+            let mut stream = self.pending.swap_remove(index).stream;
+            stream.set_nonblocking(false)?;
+            stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+            match line.parse() {
+                Ok(request) => return Ok(Some(IpcConnection { stream, request })),
+                Err(error) => {
+                    log::warn!("{}", error);
+                    let _ = IpcConnection::reply_to(&mut stream, Err(error.to_string()));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns when the oldest pending client times out, to call [`Self::accept`] again then
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending
+            .iter()
+            .map(|pending| pending.since + CLIENT_TIMEOUT)
+            .min()
+    }
+
+    /// Returns the connections waiting for the rest of their request, to be polled for input
+    pub fn pending_fds(&self) -> impl Iterator<Item = BorrowedFd<'_>> {
+        self.pending.iter().map(|pending| pending.stream.as_fd())
+    }
+}
+
+impl AsFd for IpcServer {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.listener.as_fd()
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+pub struct IpcConnection {
+    stream: UnixStream,
+    pub request: IpcRequest,
+}
+
+impl IpcConnection {
+    pub fn reply(mut self, reply: Result<String, String>) {
+        if let Err(error) = Self::reply_to(&mut self.stream, reply) {
+            log::warn!("Fail to reply to IPC request, {}", error);
+        }
+    }
+
+    fn reply_to(stream: &mut UnixStream, reply: Result<String, String>) -> std::io::Result<()> {
+        match reply {
+            Ok(body) => write!(stream, "ok\n{}", body),
+            Err(message) => write!(stream, "error\n{}", message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn parse_request() {
+        for request in [
+            IpcRequest::Status,
+            IpcRequest::Toggle,
+            IpcRequest::Set { temperature: 3500 },
+            IpcRequest::Override {
+                mode: "day".to_string(),
+                duration: Some(TimeDelta::hours(2)),
+            },
+            IpcRequest::Override {
+                mode: "late night".to_string(),
+                duration: None,
+            },
+            IpcRequest::Resume,
+        ] {
+            assert_eq!(request.to_string().parse::<IpcRequest>().unwrap(), request);
+        }
+        assert!("set temperature".parse::<IpcRequest>().is_err());
+        assert!("set temperature warm".parse::<IpcRequest>().is_err());
+        assert!("override next".parse::<IpcRequest>().is_err());
+        assert!("override 2x day".parse::<IpcRequest>().is_err());
+        assert!("pause".parse::<IpcRequest>().is_err());
+    }
+
+    #[test]
+    fn request_reply() {
+        let path = std::env::temp_dir().join(format!("wl-nightlight-{}.sock", std::process::id()));
+        let mut server = IpcServer::new(path.clone()).unwrap();
+        assert!(IpcServer::new(path.clone()).is_err());
+        assert!(server.accept().unwrap().is_none());
+
+        let client_path = path.clone();
+        let client = thread::spawn(move || {
+            (
+                send_request(&client_path, IpcRequest::Set { temperature: 3500 }),
+                send_request(&client_path, IpcRequest::Toggle),
+            )
+        });
+
+        for reply in [Ok("done".to_string()), Err("failed".to_string())] {
+            let connection = loop {
+                if let Some(connection) = server.accept().unwrap() {
+                    break connection;
+                }
+                thread::sleep(Duration::from_millis(10));
+            };
+            connection.reply(reply);
+        }
+
+        let (set, toggle) = client.join().unwrap();
+        assert_eq!(set.unwrap(), "done");
+        assert_eq!(toggle.unwrap_err().to_string(), "failed");
+
+        drop(server);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn partial_request() {
+        let path =
+            std::env::temp_dir().join(format!("wl-nightlight-partial-{}.sock", std::process::id()));
+        let mut server = IpcServer::new(path.clone()).unwrap();
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        write!(stream, "sta").unwrap();
+        assert!(server.accept().unwrap().is_none());
+        assert_eq!(server.pending_fds().count(), 1);
+
+        writeln!(stream, "tus").unwrap();
+        let connection = server.accept().unwrap().unwrap();
+        assert_eq!(connection.request, IpcRequest::Status);
+        assert_eq!(server.pending_fds().count(), 0);
+    }
+
+    #[test]
+    fn silent_client() {
+        let path =
+            std::env::temp_dir().join(format!("wl-nightlight-silent-{}.sock", std::process::id()));
+        let mut server = IpcServer::new(path.clone()).unwrap();
+        assert!(server.next_deadline().is_none());
+
+        let mut stream = UnixStream::connect(&path).unwrap();
+        write!(stream, "sta").unwrap();
+        assert!(server.accept().unwrap().is_none());
+        let deadline = server.next_deadline().unwrap();
+        assert!(deadline <= Instant::now() + CLIENT_TIMEOUT);
+
+        // Dropped once the deadline passes, without another connection or more input
+        thread::sleep(deadline.saturating_duration_since(Instant::now()));
+        assert!(server.accept().unwrap().is_none());
+        assert_eq!(server.pending_fds().count(), 0);
+        assert!(server.next_deadline().is_none());
+    }
+
+    #[test]
+    fn client_timeout() {
+        let path =
+            std::env::temp_dir().join(format!("wl-nightlight-timeout-{}.sock", std::process::id()));
+        let _listener = UnixListener::bind(&path).unwrap();
+        let start = Instant::now();
+        assert!(send_request(&path, IpcRequest::Status).is_err());
+        assert!(start.elapsed() < CLIENT_TIMEOUT * 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,232 @@
+use std::path::Path;
+
+/// Per-channel 1D lookup table, composed with the nightlight ramps of an output
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct Lut {
+    /// Red, green and blue outputs for inputs evenly spaced over the domain
+    channels: [Vec<f64>; 3],
+    /// Red, green and blue inputs of the first and last entries
+    domain: [[f64; 2]; 3],
+}
+
+impl Lut {
+    /// Reads a 1D `.cube` file, or a CSV file of `r,g,b` lines for any other extension
+    pub fn read(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|error| anyhow::anyhow!("Fail to read file {:?}, {}", path, error))?;
+        let lut = match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("cube") => parse_cube(&content),
+            _ => parse_csv(&content),
+        };
+        lut.map_err(|error| anyhow::anyhow!("Invalid LUT {:?}, {}", path, error))
+    }
+
+    /// Maps every value of the ramps through the table, resampling it to the ramp size
+    pub fn apply(&self, r: &mut [u16], g: &mut [u16], b: &mut [u16]) {
+        for (i, ramp) in [r, g, b].into_iter().enumerate() {
+            for value in ramp {
+                let output = self.sample(i, *value as f64 / u16::MAX as f64);
+                *value = (output.clamp(0.0, 1.0) * u16::MAX as f64).round() as u16;
+            }
+        }
+    }
+
+    /// Linearly interpolates `channel` at `input`, clamped to the domain
+    fn sample(&self, channel: usize, input: f64) -> f64 {
+        let entries = &self.channels[channel];
+        let [min, max] = self.domain[channel];
+        let position = ((input - min) / (max - min)).clamp(0.0, 1.0) * (entries.len() - 1) as f64;
+        let index = (position as usize).min(entries.len() - 2);
+        let a = position - index as f64;
+        entries[index] * (1.0 - a) + entries[index + 1] * a
+    }
+
+    fn new(entries: Vec<[f64; 3]>, domain: [[f64; 2]; 3]) -> anyhow::Result<Self> {
+        if entries.len() < 2 {
+            anyhow::bail!("expected at least 2 entries, found {}", entries.len());
+        }
+        if domain.iter().flatten().any(|bound| !bound.is_finite()) {
+            anyhow::bail!("domain bounds must be finite numbers");
+        }
+        if domain.iter().any(|[min, max]| min >= max) {
+            anyhow::bail!("domain minimum must be below its maximum");
+        }
+        Ok(Self {
+            channels: [0, 1, 2].map(|i| entries.iter().map(|entry| entry[i]).collect()),
+            domain,
+        })
+    }
+}
+
+/// Parses 3 numbers separated by `separator`
+fn parse_triple(line: &str, separator: impl Fn(char) -> bool) -> Option<[f64; 3]> {
+    let mut values = line.split(separator).filter(|value| !value.is_empty());
+    let triple = [values.next()?, values.next()?, values.next()?]
+        .map(|value| value.trim().parse::<f64>().ok().filter(|v| v.is_finite()));
+    match (triple, values.next()) {
+        ([Some(r), Some(g), Some(b)], None) => Some([r, g, b]),
+        _ => None,
+    }
+}
+
+/// Parses an Adobe/Resolve `.cube` file holding a 1D table
+fn parse_cube(content: &str) -> anyhow::Result<Lut> {
+    let mut size = None;
+    let mut domain = [[0.0, 1.0]; 3];
+    let mut entries = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        let error = || anyhow::anyhow!("invalid line {}: {:?}", index + 1, line);
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match keyword {
+            "" | "TITLE" => (),
+            "LUT_3D_SIZE" => anyhow::bail!("3D tables are not supported"),
+            "LUT_1D_SIZE" => size = Some(rest.trim().parse::<usize>().map_err(|_| error())?),
+            "DOMAIN_MIN" | "DOMAIN_MAX" => {
+                let bound = usize::from(keyword == "DOMAIN_MAX");
+                let values = parse_triple(rest, char::is_whitespace).ok_or_else(error)?;
+                for (channel, value) in values.into_iter().enumerate() {
+                    domain[channel][bound] = value;
+                }
+            }
+            // Resolve's equivalent of DOMAIN_MIN and DOMAIN_MAX with a single range
+            "LUT_1D_INPUT_RANGE" => {
+                let range: Vec<f64> = rest
+                    .split_whitespace()
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| error())?;
+                domain = [range.as_slice().try_into().map_err(|_| error())?; 3];
+            }
+            _ => entries.push(parse_triple(line, char::is_whitespace).ok_or_else(error)?),
+        }
+    }
+
+    match size {
+        None => anyhow::bail!("missing LUT_1D_SIZE"),
+        Some(size) if size != entries.len() => {
+            anyhow::bail!(
+                "LUT_1D_SIZE is {} but there are {} entries",
+                size,
+                entries.len()
+            )
+        }
+        Some(_) => Lut::new(entries, domain),
+    }
+}
+
+/// Parses `r,g,b` lines of outputs from 0.0 to 1.0, with an optional header line
+fn parse_csv(content: &str) -> anyhow::Result<Lut> {
+    let mut entries = Vec::new();
+    let mut header = true;
+    for (index, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        match parse_triple(line, |c| c == ',') {
+            Some(entry) => entries.push(entry),
+            None if header => (),
+            None => anyhow::bail!("invalid line {}: {:?}", index + 1, line),
+        }
+        header = false;
+    }
+    Lut::new(entries, [[0.0, 1.0]; 3])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ramps(lut: &Lut) -> [[u16; 5]; 3] {
+        let ramp = [0, 16384, 32768, 49151, 65535];
+        let (mut r, mut g, mut b) = (ramp, ramp, ramp);
+        lut.apply(&mut r, &mut g, &mut b);
+        [r, g, b]
+    }
+
+    #[test]
+    fn cube() {
+        let lut = parse_cube(
+            "
+            # Created by hand
+            TITLE \"test\"
+            LUT_1D_SIZE 3
+            DOMAIN_MIN 0.0 0.0 0.0
+            DOMAIN_MAX 1.0 1.0 1.0
+
+            0.0 0.0 0.1
+            0.5 0.25 0.55
+            1.0 0.5 1.0
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            ramps(&lut),
+            [
+                [0, 16384, 32768, 49151, 65535],
+                [0, 8192, 16384, 24576, 32768],
+                [6554, 21299, 36045, 50789, 65535],
+            ]
+        );
+
+        let ranged = parse_cube("LUT_1D_SIZE 2\nLUT_1D_INPUT_RANGE 0 0.5\n0 0 0\n1 1 1\n").unwrap();
+        assert_eq!(ranged.domain, [[0.0, 0.5]; 3]);
+        assert_eq!(ramps(&ranged)[0], [0, 32768, 65535, 65535, 65535]);
+    }
+
+    #[test]
+    fn invalid_cube() {
+        for content in [
+            "0 0 0\n1 1 1\n",
+            "LUT_1D_SIZE 3\n0 0 0\n1 1 1\n",
+            "LUT_1D_SIZE 2\n0 0\n1 1 1\n",
+            "LUT_1D_SIZE 1\n0 0 0\n",
+            "LUT_1D_SIZE 2\nDOMAIN_MAX 1 inf 1\n0 0 0\n1 1 1\n",
+            "LUT_1D_SIZE 2\nDOMAIN_MIN 0 0 nan\n0 0 0\n1 1 1\n",
+            "LUT_3D_SIZE 2\n",
+        ] {
+            assert!(parse_cube(content).is_err(), "{:?}", content);
+        }
+
+        for domain in [
+            "DOMAIN_MIN 1 1 1",
+            "LUT_1D_INPUT_RANGE nan 1",
+            "LUT_1D_INPUT_RANGE 0 inf",
+            "LUT_1D_INPUT_RANGE 0.5 0.5",
+            "LUT_1D_INPUT_RANGE 1 0",
+        ] {
+            let content = format!("LUT_1D_SIZE 2\n{}\n0 0 0\n1 1 1\n", domain);
+            let error = parse_cube(&content).unwrap_err().to_string();
+            assert!(error.starts_with("domain"), "{:?}: {}", domain, error);
+        }
+    }
+
+    #[test]
+    fn csv() {
+        let lut = parse_csv("r,g,b\n0, 0, 0\n0.5, 0.5, 0.25\n1, 1, 0.5\n").unwrap();
+        assert_eq!(
+            ramps(&lut),
+            [
+                [0, 16384, 32768, 49151, 65535],
+                [0, 16384, 32768, 49151, 65535],
+                [0, 8192, 16384, 24576, 32768],
+            ]
+        );
+
+        assert!(parse_csv("0,0,0\n").is_err());
+        assert!(parse_csv("0,0,0\nr,g,b\n1,1,1\n").is_err());
+        assert!(parse_csv("0,0,0\n1,1\n").is_err());
+    }
+
+    #[test]
+    fn read() {
+        let path = std::env::temp_dir().join(format!("wl-nightlight-{}.CUBE", std::process::id()));
+        std::fs::write(&path, "LUT_1D_SIZE 2\n0 0 0\n1 1 1\n").unwrap();
+        let lut = Lut::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(lut, parse_csv("0,0,0\n1,1,1\n").unwrap());
+
+        assert!(Lut::read(&path).is_err());
+    }
+}
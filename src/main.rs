@@ -1,25 +1,39 @@
+mod clock;
 mod color;
 mod config;
+mod ipc;
+mod lut;
+mod output;
 mod schedule;
+mod signal;
+mod watch;
 mod wayland;
+mod zone;
 
-use chrono::{Local, TimeDelta};
-use clap::Parser;
+use chrono::{DateTime, Local, NaiveDateTime, TimeDelta};
+use clap::{Args, Parser, Subcommand};
 use std::{
     fs::read_to_string,
     os::fd::{AsFd, AsRawFd},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    rc::Rc,
     sync::mpsc::channel,
     thread,
-    time::Duration,
+    time::{Duration, Instant, SystemTime},
 };
 use thiserror::Error;
 use timerfd::{SetTimeFlags, TimerFd, TimerState};
 
-use config::RawConfig;
+use clock::{Clock, SimulatedClock, SystemClock};
+use color::{Color, TEMPERATURE_MAX, TEMPERATURE_MIN};
+use config::{Config, RawConfig, parse_duration};
+use ipc::{IpcRequest, IpcServer};
 use log::LevelFilter;
+use output::OutputColors;
 use schedule::{ColorMode, ModeScheduler};
+use signal::{SignalFd, signal_name};
 use simple_logger::SimpleLogger;
+use watch::FileWatcher;
 use wayland::{Wayland, WaylandRequest};
 
 #[derive(Parser)]
@@ -34,6 +48,184 @@ struct Cli {
     /// Turn off all logs
     #[arg(short, long)]
     quiet: bool,
+    /// Runs the schedule on a virtual clock starting at a local time, e.g. `2026-12-21T00:00`
+    #[arg(long, value_name = "time", value_parser = parse_local_time)]
+    simulate_from: Option<DateTime<Local>>,
+    /// How many times faster than real time the virtual clock runs
+    #[arg(long, default_value_t = 3600.0, requires = "simulate_from", value_parser = parse_speed)]
+    speed: f64,
+    /// Logs color changes instead of applying them
+    #[arg(long)]
+    dry_run: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Shows the current mode and color
+    Status,
+    /// Switches color adjustment off or back on
+    Toggle,
+    /// Overrides the scheduled color temperature until `resume`
+    Set {
+        #[arg(long, value_parser = clap::value_parser!(u16).range(TEMPERATURE_MIN as i64..=TEMPERATURE_MAX as i64))]
+        temperature: u16,
+    },
+    /// Forces the colors of a mode until the next scheduled switch, or until `resume` when they
+    /// follow the sun's elevation
+    Override {
+        /// `day`, `night` or the name of a period
+        mode: String,
+        /// Ends the override after a duration instead, e.g. `2h` or `1h30m`
+        #[arg(long = "for", value_name = "duration", value_parser = parse_override_duration)]
+        duration: Option<TimeDelta>,
+    },
+    /// Returns to the scheduled colors
+    Resume,
+    /// Applies a color without running the schedule
+    Oneshot(OneshotArgs),
+    /// Prints the upcoming mode switches
+    Schedule {
+        #[arg(long, default_value_t = 7, value_parser = clap::value_parser!(i64).range(1..=366))]
+        days: i64,
+    },
+}
+
+#[derive(Args)]
+struct OneshotArgs {
+    /// Starts from the color of a mode in the config, `day`, `night` or the name of a period
+    #[arg(long)]
+    mode: Option<String>,
+    #[arg(long, value_parser = clap::value_parser!(u16).range(TEMPERATURE_MIN as i64..=TEMPERATURE_MAX as i64))]
+    temperature: Option<u16>,
+    #[arg(long, value_parser = parse_non_negative)]
+    gamma: Option<f64>,
+    #[arg(long, value_parser = parse_non_negative)]
+    brightness: Option<f64>,
+    /// Inverts the colors, `--inverted=false` turns off an inversion of the mode
+    #[arg(long, num_args = 0..=1, require_equals = true, default_missing_value = "true")]
+    inverted: Option<bool>,
+    /// Only changes outputs matching a pattern, can be repeated
+    #[arg(long = "output", value_name = "pattern")]
+    outputs: Vec<String>,
+    /// Keeps the color until killed, as most compositors restore the original gamma on exit
+    #[arg(long)]
+    hold: bool,
+}
+
+fn parse_non_negative(value: &str) -> Result<f64, String> {
+    value
+        .parse()
+        .ok()
+        .filter(|value: &f64| *value >= 0.0)
+        .ok_or_else(|| format!("{:?} is not a non-negative number", value))
+}
+
+fn parse_local_time(time: &str) -> Result<DateTime<Local>, String> {
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(time, format).ok())
+        .and_then(|naive_date_time| naive_date_time.and_local_timezone(Local).earliest())
+        .ok_or_else(|| format!("invalid time {:?}, expected e.g. `2026-12-21T00:00`", time))
+}
+
+fn parse_speed(speed: &str) -> Result<f64, String> {
+    speed
+        .parse()
+        .ok()
+        .filter(|speed: &f64| *speed > 0.0)
+        .ok_or_else(|| format!("{:?} is not a positive number", speed))
+}
+
+fn parse_override_duration(duration: &str) -> Result<TimeDelta, String> {
+    parse_duration(duration)
+        .ok()
+        .filter(|duration| *duration > TimeDelta::zero())
+        .ok_or_else(|| {
+            format!(
+                "invalid duration {:?}, expected e.g. `2h` or `1h30m`",
+                duration
+            )
+        })
+}
+
+/// Adjustments requested over IPC or signals on top of the schedule
+#[derive(Default)]
+struct Manual {
+    disabled: bool,
+    temperature: Option<u16>,
+    /// Mode forced in place of the scheduled one until `override_timerfd` expires
+    mode: Option<ColorMode>,
+}
+
+impl Manual {
+    /// Forces `mode` for `duration`, or until the next scheduled switch if `None`
+    ///
+    /// Returns how long the override lasts, `None` until ended when there are no switches
+    fn start_override(
+        &mut self,
+        mode: ColorMode,
+        duration: Option<TimeDelta>,
+        mode_scheduler: &mut ModeScheduler,
+        override_timerfd: &mut TimerFd,
+    ) -> Option<TimeDelta> {
+        // Bring `switch_ms` up to date, it may have been computed long ago
+        mode_scheduler.next();
+        let duration = duration.or(mode_scheduler.switch_ms.map(TimeDelta::milliseconds));
+        *self = Manual {
+            mode: Some(mode),
+            ..Manual::default()
+        };
+        override_timerfd.set_state(
+            match duration {
+                Some(duration) => TimerState::Oneshot(Duration::from_millis(
+                    mode_scheduler
+                        .clock()
+                        .real_ms(duration.num_milliseconds())
+                        .max(1) as u64,
+                )),
+                None => TimerState::Disarmed,
+            },
+            SetTimeFlags::Default,
+        );
+        duration
+    }
+
+    fn end_override(&mut self, override_timerfd: &mut TimerFd) {
+        self.mode = None;
+        override_timerfd.set_state(TimerState::Disarmed, SetTimeFlags::Default);
+    }
+
+    fn resume(&mut self, override_timerfd: &mut TimerFd) {
+        *self = Manual::default();
+        override_timerfd.set_state(TimerState::Disarmed, SetTimeFlags::Default);
+    }
+
+    fn apply(&self, color: Color) -> Color {
+        if self.disabled {
+            Color::default()
+        } else if let Some(temperature) = self.temperature {
+            Color {
+                temperature,
+                ..color
+            }
+        } else {
+            color
+        }
+    }
+
+    fn state(&self) -> &str {
+        if self.disabled {
+            "disabled"
+        } else if self.mode.is_some() {
+            "override"
+        } else if self.temperature.is_some() {
+            "manual"
+        } else {
+            "scheduled"
+        }
+    }
 }
 
 #[derive(Error, Debug)]
@@ -42,6 +234,115 @@ pub struct InternalError<'a> {
     message: &'a str,
 }
 
+fn read_config(path: &Path) -> anyhow::Result<Config> {
+    let content = &read_to_string(path)
+        .map_err(|error| anyhow::anyhow!("Fail to read file {:?}, {}", path, error))?;
+    RawConfig::read(content)?
+        .relative_to(path.parent().unwrap_or(Path::new("")))
+        .check()
+}
+
+fn oneshot(args: OneshotArgs, config_path: &Path) -> anyhow::Result<()> {
+    let mut colors = match &args.mode {
+        Some(name) => {
+            let config = read_config(config_path)?;
+            let mode = config
+                .mode_by_name(name)
+                .ok_or_else(|| anyhow::anyhow!("Unknown mode {:?}", name))?;
+            config.output_colors(mode, mode, 1.0)
+        }
+        None => OutputColors {
+            default: Color::default(),
+            outputs: Vec::new(),
+            luts: Vec::new(),
+            selected: Vec::new(),
+        },
+    }
+    .map(|color| Color {
+        temperature: args.temperature.unwrap_or(color.temperature),
+        gamma: args.gamma.map_or(color.gamma, |gamma| [gamma; 3]),
+        brightness: args.brightness.unwrap_or(color.brightness),
+        inverted: args.inverted.unwrap_or(color.inverted),
+        ..color
+    });
+    // Leave every output that is not selected untouched
+    colors.selected = args.outputs;
+
+    // Block signals before spawning the Wayland thread so that only the signalfd receives them
+    let mut signalfd = SignalFd::new(&[libc::SIGINT, libc::SIGTERM])?;
+
+    let (wayland_sender, wayland_receiver) = channel();
+    let (mut wayland, request_sender) = Wayland::new(wayland_sender)?;
+    thread::spawn(move || {
+        wayland.process_requests();
+    });
+
+    request_sender.send(WaylandRequest::ChangeOutputColor(colors))?;
+    wayland_receiver.recv()??;
+
+    if args.hold {
+        log::info!("Holding color until killed");
+        let signal = signalfd.read()?;
+        log::info!(
+            "Received {}, restoring gamma and exiting",
+            signal_name(signal)
+        );
+        request_sender.send(WaylandRequest::Shutdown)?;
+        wayland_receiver.recv()??;
+    }
+    Ok(())
+}
+
+fn print_schedule(days: i64, config_path: &Path) -> anyhow::Result<()> {
+    let config = read_config(config_path)?;
+    let mode_scheduler = ModeScheduler::new(config.schedule.clone(), config.location.clone())?;
+
+    let Some(switches) = mode_scheduler.timeline(days) else {
+        println!("Colors follow the sun's elevation, there are no fixed switch times");
+        return Ok(());
+    };
+    for switch in switches {
+        let color = config.color(switch.mode);
+        println!(
+            "{}  {:<12} {:>5}K gamma {:<4} brightness {:<4}{}  {}",
+            switch
+                .date_time
+                .with_timezone(&Local)
+                .format("%a %Y-%m-%d %H:%M"),
+            format!("[{}]", config.mode_name(switch.mode)),
+            color.temperature,
+            color.gamma_str(),
+            color.brightness,
+            if color.inverted { " inverted" } else { "" },
+            switch.source,
+        );
+    }
+    Ok(())
+}
+
+/// Drains an expired timer, returning whether it was canceled by a change of the real-time clock
+fn timer_canceled(timerfd: &TimerFd) -> std::io::Result<bool> {
+    let mut expirations = [0u8; 8];
+    if unsafe { libc::read(timerfd.as_raw_fd(), expirations.as_mut_ptr().cast(), 8) } == -1 {
+        let err = std::io::Error::last_os_error();
+        return match err.raw_os_error() {
+            Some(libc::ECANCELED) => Ok(true),
+            Some(libc::EAGAIN) => Ok(false),
+            _ => Err(err),
+        };
+    }
+    Ok(false)
+}
+
+/// Opens the control socket, except for simulations and dry runs so that they can run next to the
+/// daemon without taking over its socket
+fn open_ipc_server(path: PathBuf, simulated: bool) -> anyhow::Result<Option<IpcServer>> {
+    if simulated {
+        return Ok(None);
+    }
+    Ok(Some(IpcServer::new(path)?))
+}
+
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
@@ -72,49 +373,179 @@ fn main() -> anyhow::Result<()> {
             p
         }))
         .ok_or_else(|| anyhow::anyhow!("Unable to locate config file"))?;
-    let content = &read_to_string(&path)
-        .map_err(|error| anyhow::anyhow!("Fail to read file {:?}, {}", &path, error))?;
-    let config = RawConfig::read(content)?.check()?;
 
-    let (request_sender, request_receiver) = channel();
-    let (wayland_sender, wayland_receiver) = channel();
-    let mut wayland = Wayland::new(wayland_sender, request_receiver)?;
+    if let Some(command) = cli.command {
+        let request = match command {
+            Command::Status => IpcRequest::Status,
+            Command::Toggle => IpcRequest::Toggle,
+            Command::Set { temperature } => IpcRequest::Set { temperature },
+            Command::Override { mode, duration } => IpcRequest::Override { mode, duration },
+            Command::Resume => IpcRequest::Resume,
+            Command::Oneshot(args) => return oneshot(args, &path),
+            Command::Schedule { days } => return print_schedule(days, &path),
+        };
+        print!("{}", ipc::send_request(&ipc::socket_path()?, request)?);
+        return Ok(());
+    }
+    // Simulations and dry runs only log colors, and follow the virtual clock or none at all
+    let simulated = cli.dry_run || cli.simulate_from.is_some();
+    let mut config = read_config(&path)?;
+    let mut watcher = FileWatcher::new(&path)?;
+    let mut timezone_watcher = if simulated {
+        None
+    } else {
+        Some(FileWatcher::new_link(Path::new("/etc/localtime"))?)
+    };
+    let mut ipc_server = open_ipc_server(ipc::socket_path()?, simulated)?;
 
-    thread::spawn(move || {
-        wayland.process_requests();
-    });
+    // Block signals before spawning the Wayland thread so that only the signalfd receives them
+    let mut signalfd = SignalFd::new(&[libc::SIGINT, libc::SIGTERM, libc::SIGHUP, libc::SIGUSR1])?;
 
-    let mut mode_scheduler = ModeScheduler::new(config.schedule, config.location)?;
-    let mut timerfd = TimerFd::new_custom(timerfd::ClockId::Boottime, false, false)?;
-    let mut poll_array = [libc::pollfd {
-        fd: timerfd.as_fd().as_raw_fd(),
-        events: libc::POLLIN,
-        revents: 0,
-    }];
+    // Without Wayland on dry runs, colors are only logged
+    let wayland = if cli.dry_run {
+        None
+    } else {
+        let (wayland_sender, wayland_receiver) = channel();
+        let (mut wayland, request_sender) = Wayland::new(wayland_sender)?;
 
-    loop {
-        log::info!("Enter {} mode", mode_scheduler.mode);
+        thread::spawn(move || {
+            wayland.process_requests();
+        });
+        Some((request_sender, wayland_receiver))
+    };
 
-        request_sender.send(WaylandRequest::ChangeOutputColor(
-            match mode_scheduler.mode {
-                ColorMode::Day => config.day,
-                ColorMode::Night => config.night,
-            },
-        ))?;
-        wayland_receiver.recv()??;
+    let clock: Rc<dyn Clock> = match cli.simulate_from {
+        Some(start) => {
+            log::info!(
+                "Simulate from {} at {}x speed",
+                start.format("%Y-%m-%d %H:%M"),
+                cli.speed
+            );
+            Rc::new(SimulatedClock::new(start.to_utc(), cli.speed))
+        }
+        None => Rc::new(SystemClock),
+    };
 
-        log::info!(
-            "Next mode switch at {}",
-            (Local::now() + TimeDelta::milliseconds(mode_scheduler.delay_ms))
-                .format("%Y-%m-%d %H:%M")
-        );
+    let mut mode_scheduler = ModeScheduler::with_clock(
+        config.schedule.clone(),
+        config.location.clone(),
+        clock.clone(),
+    )?;
+    // An absolute real-time timer fires on time after suspend and is canceled when the clock is set
+    let mut timerfd = TimerFd::new_custom(timerfd::ClockId::Realtime, true, true)?;
+    let mut override_timerfd = TimerFd::new_custom(timerfd::ClockId::Boottime, false, false)?;
+    let mut poll_array = [
+        libc::pollfd {
+            fd: timerfd.as_fd().as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        },
+        libc::pollfd {
+            fd: signalfd.as_fd().as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        },
+        libc::pollfd {
+            fd: watcher.as_fd().as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        },
+        libc::pollfd {
+            fd: ipc_server
+                .as_ref()
+                .map_or(-1, |ipc_server| ipc_server.as_fd().as_raw_fd()),
+            events: libc::POLLIN,
+            revents: 0,
+        },
+        libc::pollfd {
+            fd: override_timerfd.as_fd().as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        },
+        libc::pollfd {
+            fd: timezone_watcher
+                .as_ref()
+                .map_or(-1, |timezone_watcher| timezone_watcher.as_fd().as_raw_fd()),
+            events: libc::POLLIN,
+            revents: 0,
+        },
+    ];
+
+    let mut manual = Manual::default();
+    let mut current_mode = None;
+    let mut current_color = None;
+    loop {
+        if current_mode != Some(mode_scheduler.mode) {
+            log::info!("Enter [{}] mode", config.mode_name(mode_scheduler.mode));
+            if let Some(switch_ms) = mode_scheduler.switch_ms {
+                log::info!(
+                    "Next mode switch at {}",
+                    (clock.now() + TimeDelta::milliseconds(switch_ms))
+                        .with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M")
+                );
+            }
+            current_mode = Some(mode_scheduler.mode);
+        }
+
+        let colors = match manual.mode {
+            Some(mode) => config.output_colors(mode, mode, 1.0),
+            None => config.output_colors(
+                mode_scheduler.previous_mode,
+                mode_scheduler.mode,
+                mode_scheduler.progress,
+            ),
+        }
+        .map(|color| manual.apply(color));
+        if simulated && current_color != Some(colors.default) {
+            let color = colors.default;
+            log::info!(
+                "{}: {}K, gamma {}, brightness {}{}",
+                clock.now().with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                color.temperature,
+                color.gamma_str(),
+                color.brightness,
+                if color.inverted { ", inverted" } else { "" },
+            );
+            current_color = Some(color);
+        }
+        if let Some((request_sender, wayland_receiver)) = &wayland {
+            request_sender.send(WaylandRequest::ChangeOutputColor(colors.clone()))?;
+            wayland_receiver.recv()??;
+        }
 
+        let deadline = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?
+            + Duration::from_millis(clock.real_ms(mode_scheduler.delay_ms) as u64);
         timerfd.set_state(
-            TimerState::Oneshot(Duration::from_millis(mode_scheduler.delay_ms as u64)),
-            SetTimeFlags::Default,
+            TimerState::Oneshot(deadline),
+            if simulated {
+                SetTimeFlags::Abstime
+            } else {
+                SetTimeFlags::TimerCancelOnSet
+            },
         );
+        // IPC clients that have not sent their whole request yet are polled after the fixed fds
+        let mut poll_fds = poll_array.to_vec();
+        poll_fds.extend(
+            ipc_server
+                .iter()
+                .flat_map(IpcServer::pending_fds)
+                .map(|fd| libc::pollfd {
+                    fd: fd.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                }),
+        );
+        // Wake up for the first pending IPC client to time out, even if nothing else happens
+        let ipc_deadline = ipc_server.as_ref().and_then(IpcServer::next_deadline);
         loop {
-            if unsafe { libc::poll(poll_array.as_mut_ptr(), poll_array.len() as _, -1) } == -1 {
+            let timeout = ipc_deadline.map_or(-1, |deadline| {
+                deadline
+                    .saturating_duration_since(Instant::now())
+                    .as_nanos()
+                    .div_ceil(1_000_000) as libc::c_int
+            });
+            if unsafe { libc::poll(poll_fds.as_mut_ptr(), poll_fds.len() as _, timeout) } == -1 {
                 let err = std::io::Error::last_os_error();
                 if err.kind() == std::io::ErrorKind::Interrupted {
                     continue;
@@ -123,6 +554,190 @@ fn main() -> anyhow::Result<()> {
             }
             break;
         }
+        let (fixed_fds, pending_fds) = poll_fds.split_at(poll_array.len());
+        let ipc_pending = pending_fds.iter().any(|pollfd| pollfd.revents != 0)
+            || ipc_deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        poll_array.copy_from_slice(fixed_fds);
+
+        if poll_array[0].revents != 0 && timer_canceled(&timerfd)? {
+            log::info!("System clock changed, recomputing schedule");
+            current_mode = None;
+        }
+        if poll_array[5].revents != 0
+            && let Some(timezone_watcher) = &mut timezone_watcher
+            && timezone_watcher.changed()?
+        {
+            log::info!("Time zone changed, recomputing schedule");
+            current_mode = None;
+        }
+
+        let mut reload = false;
+        if poll_array[1].revents != 0 {
+            let signal = signalfd.read()?;
+            if signal == libc::SIGHUP {
+                log::info!("Received {}, reloading config", signal_name(signal));
+                reload = true;
+            } else if signal == libc::SIGUSR1 {
+                if manual.mode.is_some() {
+                    log::info!("Received {}, ending override", signal_name(signal));
+                    manual.end_override(&mut override_timerfd);
+                } else {
+                    let until = match manual.start_override(
+                        ColorMode::Day,
+                        None,
+                        &mut mode_scheduler,
+                        &mut override_timerfd,
+                    ) {
+                        Some(_) => "until the next scheduled switch",
+                        // Colors follow the sun's elevation, without switches
+                        None => "until it is sent again",
+                    };
+                    log::info!(
+                        "Received {}, forcing [day] mode {}",
+                        signal_name(signal),
+                        until
+                    );
+                }
+            } else {
+                log::info!(
+                    "Received {}, restoring gamma and exiting",
+                    signal_name(signal)
+                );
+                if let Some((request_sender, wayland_receiver)) = &wayland {
+                    request_sender.send(WaylandRequest::Shutdown)?;
+                    wayland_receiver.recv()??;
+                }
+                return Ok(());
+            }
+        }
+        if poll_array[2].revents != 0 && watcher.changed()? {
+            log::info!("Config file {:?} changed, reloading config", path);
+            reload = true;
+        }
+
+        if poll_array[4].revents != 0 {
+            override_timerfd.read();
+            if manual.mode.take().is_some() {
+                log::info!("Override expired, resume schedule");
+            }
+        }
+
+        if (poll_array[3].revents != 0 || ipc_pending)
+            && let Some(ipc_server) = &mut ipc_server
+        {
+            while let Some(connection) = ipc_server.accept()? {
+                log::debug!("Received IPC request `{}`", connection.request);
+                let reply = match &connection.request {
+                    IpcRequest::Status => {
+                        let color = colors.default;
+                        Ok(format!(
+                            "mode: {}\nstate: {}\ntemperature: {}\ngamma: {}\nbrightness: {}\ninverted: {}\n",
+                            config.mode_name(manual.mode.unwrap_or(mode_scheduler.mode)),
+                            manual.state(),
+                            color.temperature,
+                            color.gamma_str(),
+                            color.brightness,
+                            color.inverted,
+                        ))
+                    }
+                    IpcRequest::Toggle => {
+                        manual.disabled = !manual.disabled;
+                        log::info!("Color adjustment {}", manual.state());
+                        Ok(format!("{}\n", manual.state()))
+                    }
+                    IpcRequest::Set { temperature } => {
+                        let temperature = *temperature;
+                        if (TEMPERATURE_MIN..=TEMPERATURE_MAX).contains(&temperature) {
+                            manual.resume(&mut override_timerfd);
+                            manual.temperature = Some(temperature);
+                            log::info!("Temperature set to {}K until resumed", temperature);
+                            Ok(String::new())
+                        } else {
+                            Err(format!(
+                                "Temperature {} is outside of {}..={}",
+                                temperature, TEMPERATURE_MIN, TEMPERATURE_MAX
+                            ))
+                        }
+                    }
+                    IpcRequest::Override { mode, duration } => match config.mode_by_name(mode) {
+                        Some(mode) => {
+                            match manual.start_override(
+                                mode,
+                                *duration,
+                                &mut mode_scheduler,
+                                &mut override_timerfd,
+                            ) {
+                                Some(duration) => log::info!(
+                                    "Force [{}] mode until {}",
+                                    config.mode_name(mode),
+                                    (clock.now() + duration)
+                                        .with_timezone(&Local)
+                                        .format("%Y-%m-%d %H:%M")
+                                ),
+                                None => log::info!(
+                                    "Force [{}] mode until resumed",
+                                    config.mode_name(mode)
+                                ),
+                            }
+                            Ok(String::new())
+                        }
+                        None => Err(format!("Unknown mode {:?}", mode)),
+                    },
+                    IpcRequest::Resume => {
+                        manual.resume(&mut override_timerfd);
+                        log::info!("Resume schedule");
+                        Ok(String::new())
+                    }
+                };
+                connection.reply(reply);
+            }
+        }
+
+        if reload {
+            // Keep running with the previous config if the new one is invalid
+            match read_config(&path).and_then(|new_config| {
+                let new_scheduler = ModeScheduler::with_clock(
+                    new_config.schedule.clone(),
+                    new_config.location.clone(),
+                    clock.clone(),
+                )?;
+                Ok((new_config, new_scheduler))
+            }) {
+                Ok((new_config, new_scheduler)) => {
+                    // Periods may have changed, so a forced one may no longer exist
+                    if manual.mode.is_some() {
+                        log::info!("Config reloaded, ending override");
+                        manual.end_override(&mut override_timerfd);
+                    }
+                    config = new_config;
+                    mode_scheduler = new_scheduler;
+                    current_mode = None;
+                    continue;
+                }
+                Err(error) => {
+                    log::error!("Fail to reload config, keeping the previous one\n{}", error)
+                }
+            }
+        }
         mode_scheduler.next();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn simulate_next_to_daemon() {
+        let path = std::env::temp_dir().join(format!(
+            "wl-nightlight-simulate-{}.sock",
+            std::process::id()
+        ));
+        let daemon = open_ipc_server(path.clone(), false).unwrap();
+        assert!(daemon.is_some());
+
+        assert!(open_ipc_server(path.clone(), true).unwrap().is_none());
+        assert!(open_ipc_server(path.clone(), false).is_err());
+        assert!(path.exists());
+    }
+}
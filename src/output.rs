@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use crate::{color::Color, lut::Lut};
+
+/// Attributes an output can be matched by
+#[derive(Default)]
+#[cfg_attr(test, derive(Debug))]
+pub struct OutputInfo {
+    /// Connector name, e.g. `DP-1`
+    pub name: Option<String>,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub description: Option<String>,
+}
+
+impl OutputInfo {
+    /// Returns the name, `make model` and description, whichever are known
+    fn identifiers(&self) -> Vec<String> {
+        let make_model = match (&self.make, &self.model) {
+            (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+            _ => None,
+        };
+        [self.name.clone(), make_model, self.description.clone()]
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+impl std::fmt::Display for OutputInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let unknown = "unknown".to_string();
+        write!(
+            f,
+            "name: {}, make: {}, model: {}, description: {}",
+            self.name.as_ref().unwrap_or(&unknown),
+            self.make.as_ref().unwrap_or(&unknown),
+            self.model.as_ref().unwrap_or(&unknown),
+            self.description.as_ref().unwrap_or(&unknown),
+        )
+    }
+}
+
+/// Colors to apply to every output at one point in time
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub struct OutputColors {
+    pub default: Color,
+    /// Colors by output pattern, `None` for outputs that are left untouched
+    pub outputs: Vec<(String, Option<Color>)>,
+    /// Lookup tables by output pattern, composed with the colors
+    pub luts: Vec<(String, Arc<Lut>)>,
+    /// Patterns of the outputs to change, leaving the others untouched, every output when empty
+    pub selected: Vec<String>,
+}
+
+impl OutputColors {
+    /// Returns the color for an output, preferring exact matches over wildcard ones
+    ///
+    /// Patterns are matched against the output name, its `make model` and its description.
+    pub fn get(&self, info: &OutputInfo) -> Option<Color> {
+        if !self.selected.is_empty()
+            && !info.identifiers().iter().any(|identifier| {
+                self.selected
+                    .iter()
+                    .any(|pattern| glob_match(pattern, identifier))
+            })
+        {
+            return None;
+        }
+        find_match(&self.outputs, info).map_or(Some(self.default), |color| *color)
+    }
+
+    /// Returns the lookup table for an output, matched like its color
+    pub fn lut(&self, info: &OutputInfo) -> Option<Arc<Lut>> {
+        find_match(&self.luts, info).cloned()
+    }
+
+    /// Replaces every color with `f(color)`, leaving untouched outputs as they are
+    pub fn map(mut self, f: impl Fn(Color) -> Color) -> Self {
+        self.default = f(self.default);
+        for (_, color) in &mut self.outputs {
+            *color = color.map(&f);
+        }
+        self
+    }
+}
+
+/// Returns the value of the first entry whose pattern matches exactly, or else with wildcards
+fn find_match<'a, T>(entries: &'a [(String, T)], info: &OutputInfo) -> Option<&'a T> {
+    let identifiers = info.identifiers();
+    entries
+        .iter()
+        .find(|(pattern, _)| identifiers.contains(pattern))
+        .or_else(|| {
+            entries.iter().find(|(pattern, _)| {
+                identifiers
+                    .iter()
+                    .any(|identifier| glob_match(pattern, identifier))
+            })
+        })
+        .map(|(_, value)| value)
+}
+
+/// Matches `text` against `pattern`, where `*` matches any sequence and `?` any single character
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_t)) => {
+                    backtrack = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn glob() {
+        assert!(glob_match("DP-1", "DP-1"));
+        assert!(!glob_match("DP-1", "DP-12"));
+        assert!(glob_match("DP-*", "DP-12"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("HDMI-?-1", "HDMI-A-1"));
+        assert!(!glob_match("HDMI-?-1", "HDMI-AB-1"));
+        assert!(glob_match("*-1", "HDMI-A-1"));
+        assert!(glob_match("e*P*1", "eDP-1"));
+        assert!(!glob_match("e*P*2", "eDP-1"));
+    }
+
+    #[test]
+    fn get() {
+        let night = Color {
+            temperature: 3000,
+            ..Color::default()
+        };
+        let colors = OutputColors {
+            default: Color::default(),
+            outputs: vec![
+                ("DP-*".to_string(), None),
+                ("DP-2".to_string(), Some(night)),
+            ],
+            luts: Vec::new(),
+            selected: Vec::new(),
+        };
+
+        let named = |name: &str| OutputInfo {
+            name: Some(name.to_string()),
+            ..OutputInfo::default()
+        };
+
+        assert_eq!(colors.get(&OutputInfo::default()), Some(Color::default()));
+        assert_eq!(colors.get(&named("eDP-1")), Some(Color::default()));
+        assert_eq!(colors.get(&named("DP-1")), None);
+        assert_eq!(colors.get(&named("DP-2")), Some(night));
+
+        let selected = OutputColors {
+            selected: vec!["eDP-*".to_string(), "DP-2".to_string()],
+            ..colors
+        };
+        assert_eq!(selected.get(&named("eDP-1")), Some(Color::default()));
+        assert_eq!(selected.get(&named("DP-2")), Some(night));
+        assert_eq!(selected.get(&named("HDMI-A-1")), None);
+    }
+
+    #[test]
+    fn get_overlapping_wildcards() {
+        let night = Color {
+            temperature: 3000,
+            ..Color::default()
+        };
+        let colors = OutputColors {
+            default: Color::default(),
+            outputs: vec![("DP-*".to_string(), Some(night)), ("*-1".to_string(), None)],
+            luts: Vec::new(),
+            selected: Vec::new(),
+        };
+
+        let named = |name: &str| OutputInfo {
+            name: Some(name.to_string()),
+            ..OutputInfo::default()
+        };
+
+        // The first matching pattern of the file wins
+        assert_eq!(colors.get(&named("DP-1")), Some(night));
+        assert_eq!(colors.get(&named("HDMI-A-1")), None);
+
+        let reversed = OutputColors {
+            outputs: colors.outputs.iter().rev().cloned().collect(),
+            ..colors
+        };
+        assert_eq!(reversed.get(&named("DP-1")), None);
+        assert_eq!(reversed.get(&named("DP-2")), Some(night));
+    }
+
+    #[test]
+    fn get_by_make_model_description() {
+        let night = Color {
+            temperature: 3000,
+            ..Color::default()
+        };
+        let colors = OutputColors {
+            default: Color::default(),
+            outputs: vec![
+                ("Dell Inc. U2720Q".to_string(), Some(night)),
+                ("*BOE*".to_string(), None),
+            ],
+            luts: Vec::new(),
+            selected: Vec::new(),
+        };
+
+        let dell = OutputInfo {
+            name: Some("DP-3".to_string()),
+            make: Some("Dell Inc.".to_string()),
+            model: Some("U2720Q".to_string()),
+            description: None,
+        };
+        assert_eq!(colors.get(&dell), Some(night));
+
+        let laptop = OutputInfo {
+            name: Some("eDP-1".to_string()),
+            description: Some("BOE 0x0BCA (eDP-1)".to_string()),
+            ..OutputInfo::default()
+        };
+        assert_eq!(colors.get(&laptop), None);
+    }
+}
@@ -1,38 +1,61 @@
-#[cfg(not(test))]
-use chrono::{Local, Utc};
-#[cfg(test)]
-use mock_chrono::{Local, Utc};
+use std::rc::Rc;
 
-use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use chrono::{
+    DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Weekday,
+};
 use sunrise::{
-    Coordinates, SolarDay,
+    Coordinates, DawnType, SolarDay,
     SolarEvent::{self, Sunrise, Sunset},
 };
 
 use crate::{
     InternalError,
-    config::{Location, Schedule, ScheduleType},
+    clock::{Clock, SystemClock},
+    config::{Exception, Location, PolarFallback, Schedule, ScheduleType, solar_event_name},
+    zone::Zone,
 };
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(test, derive(Debug))]
 pub enum ColorMode {
     Day,
     Night,
+    /// Index into the extra periods of the config
+    Period(usize),
 }
 
-#[cfg(not(tarpaulin_include))]
-impl std::fmt::Display for ColorMode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match *self {
-            Self::Day => write!(f, "[day]"),
-            Self::Night => write!(f, "[night]"),
+impl ColorMode {
+    /// Maps an index into `[day, night, periods...]` to its mode
+    fn from_index(index: usize) -> Self {
+        match index {
+            0 => Self::Day,
+            1 => Self::Night,
+            _ => Self::Period(index - 2),
+        }
+    }
+
+    /// Index of the mode into `[day, night, periods...]`
+    pub fn index(self) -> usize {
+        match self {
+            Self::Day => 0,
+            Self::Night => 1,
+            Self::Period(index) => index + 2,
         }
     }
 }
 
+/// Side of a solar event's elevation the sun stays on during a date where the event does not occur
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(test, derive(Debug))]
+enum Polar {
+    Day,
+    Night,
+}
+
 trait Scheduler {
-    fn get(&self, date: NaiveDate) -> DateTime<chrono::Utc>;
+    fn get(&self, date: NaiveDate) -> Result<DateTime<chrono::Utc>, Polar>;
+    /// Describes where the time on `date` comes from, e.g. `auto (sunset)`
+    fn source(&self, date: NaiveDate) -> String;
 }
 
 struct AutoScheduler {
@@ -40,22 +63,80 @@ struct AutoScheduler {
     event_type: SolarEvent,
 }
 
+impl AutoScheduler {
+    /// Returns the time of the event on `date`, or if the sun rises and sets but does not reach
+    /// the elevation of the event, the time it comes closest with a description of that time
+    ///
+    /// Fails if the sun does not rise or set on `date`.
+    fn event_time(
+        &self,
+        date: NaiveDate,
+    ) -> Result<(DateTime<chrono::Utc>, Option<&'static str>), Polar> {
+        let solar_day = SolarDay::new(self.coordinates, date);
+        let date_time = solar_day.event_time(self.event_type);
+        // `event_time` returns the Unix epoch when the event does not occur
+        if date_time != DateTime::UNIX_EPOCH {
+            return Ok((date_time, None));
+        }
+
+        let solar_midnight = NaiveDateTime::new(date, NaiveTime::MIN).and_utc()
+            - TimeDelta::seconds((self.coordinates.lon() * 240.0) as i64);
+        let polar = if solar_elevation(self.coordinates, solar_midnight)
+            > event_elevation(self.event_type)
+        {
+            Polar::Day
+        } else {
+            Polar::Night
+        };
+        if [Sunrise, Sunset]
+            .iter()
+            .any(|event| solar_day.event_time(*event) == DateTime::UNIX_EPOCH)
+        {
+            return Err(polar);
+        }
+
+        let morning = matches!(
+            self.event_type,
+            Sunrise | SolarEvent::Dawn(_) | SolarEvent::Elevation { morning: true, .. }
+        );
+        Ok(match polar {
+            // The sun stays above the event, it is lowest at the solar midnight on its side
+            Polar::Day if morning => (solar_midnight, Some("solar midnight")),
+            Polar::Day => (solar_midnight + TimeDelta::days(1), Some("solar midnight")),
+            Polar::Night => (solar_midnight + TimeDelta::hours(12), Some("solar noon")),
+        })
+    }
+}
+
 impl Scheduler for AutoScheduler {
-    fn get(&self, date: NaiveDate) -> DateTime<chrono::Utc> {
-        SolarDay::new(self.coordinates, date).event_time(self.event_type)
+    fn get(&self, date: NaiveDate) -> Result<DateTime<chrono::Utc>, Polar> {
+        self.event_time(date).map(|(date_time, _)| date_time)
+    }
+
+    fn source(&self, date: NaiveDate) -> String {
+        match self.event_time(date) {
+            Ok((_, Some(substitute))) => format!(
+                "auto ({} does not occur, {})",
+                solar_event_name(self.event_type),
+                substitute
+            ),
+            _ => format!("auto ({})", solar_event_name(self.event_type)),
+        }
     }
 }
 
 struct FixedScheduler {
     naive_time: NaiveTime,
+    zone: Zone,
 }
 
 impl Scheduler for FixedScheduler {
-    fn get(&self, date: NaiveDate) -> DateTime<chrono::Utc> {
-        NaiveDateTime::new(date, self.naive_time)
-            .and_local_timezone(Local)
-            .unwrap()
-            .to_utc()
+    fn get(&self, date: NaiveDate) -> Result<DateTime<chrono::Utc>, Polar> {
+        Ok(self.zone.resolve(NaiveDateTime::new(date, self.naive_time)))
+    }
+
+    fn source(&self, _date: NaiveDate) -> String {
+        "fixed".to_string()
     }
 }
 
@@ -65,22 +146,198 @@ struct RelativeScheduler {
 }
 
 impl Scheduler for RelativeScheduler {
-    fn get(&self, date: NaiveDate) -> DateTime<chrono::Utc> {
-        self.auto_scheduler.get(date) + self.time_delta
+    fn get(&self, date: NaiveDate) -> Result<DateTime<chrono::Utc>, Polar> {
+        Ok(self.auto_scheduler.get(date)? + self.time_delta)
+    }
+
+    fn source(&self, _date: NaiveDate) -> String {
+        let minutes = self.time_delta.num_minutes();
+        format!(
+            "relative ({} {}{:02}:{:02})",
+            solar_event_name(self.auto_scheduler.event_type),
+            if minutes < 0 { '-' } else { '+' },
+            minutes.abs() / 60,
+            minutes.abs() % 60
+        )
+    }
+}
+
+/// Picks the scheduler of the first exception containing each date, or else of its day of the
+/// week
+struct DateScheduler {
+    exceptions: Vec<(Exception, Box<dyn Scheduler>)>,
+    /// Schedulers from Monday to Sunday
+    weekdays: Vec<Box<dyn Scheduler>>,
+}
+
+impl DateScheduler {
+    fn exception(&self, date: NaiveDate) -> Option<&dyn Scheduler> {
+        self.exceptions
+            .iter()
+            .find(|(exception, _)| exception.contains(date))
+            .map(|(_, scheduler)| scheduler.as_ref())
+    }
+
+    fn weekday(&self, date: NaiveDate) -> &dyn Scheduler {
+        self.weekdays[date.weekday().num_days_from_monday() as usize].as_ref()
     }
 }
 
+impl Scheduler for DateScheduler {
+    fn get(&self, date: NaiveDate) -> Result<DateTime<chrono::Utc>, Polar> {
+        self.exception(date)
+            .unwrap_or_else(|| self.weekday(date))
+            .get(date)
+    }
+
+    fn source(&self, date: NaiveDate) -> String {
+        match self.exception(date) {
+            Some(scheduler) => format!("{} on exception", scheduler.source(date)),
+            None => format!(
+                "{} on {}",
+                self.weekday(date).source(date),
+                date.format("%A")
+            ),
+        }
+    }
+}
+
+/// Sun elevation in degrees at which a solar event occurs
+fn event_elevation(event: SolarEvent) -> f64 {
+    match event {
+        Sunrise | Sunset => -0.833,
+        SolarEvent::Dawn(dawn_type) | SolarEvent::Dusk(dawn_type) => match dawn_type {
+            DawnType::Civil => -6.0,
+            DawnType::Nautical => -12.0,
+            DawnType::Astronomical => -18.0,
+        },
+        SolarEvent::Elevation { elevation, .. } => elevation.to_degrees(),
+    }
+}
+
+/// Upper bound on the number of color updates during a transition
+const TRANSITION_STEPS: i64 = 300;
+/// Lower bound on the delay between two color updates during a transition
+const TRANSITION_STEP_MIN_MS: i64 = 100;
+/// Delay between two color updates while the sun is between the day and night elevations
+const ELEVATION_STEP_MS: i64 = 10_000;
+/// Delay between two elevation checks while the sun is outside of the transition band
+const ELEVATION_IDLE_MS: i64 = 60_000;
+
+/// Computes the sun's elevation above the horizon in degrees
+fn solar_elevation(coordinates: Coordinates, date_time: DateTime<chrono::Utc>) -> f64 {
+    let days = date_time.timestamp_millis() as f64 / 86_400_000.0 - 10_957.5;
+
+    let mean_longitude = (280.460 + 0.985_647_4 * days).to_radians();
+    let mean_anomaly = (357.528 + 0.985_600_3 * days).to_radians();
+    let ecliptic_longitude = mean_longitude
+        + 1.915_f64.to_radians() * mean_anomaly.sin()
+        + 0.020_f64.to_radians() * (2.0 * mean_anomaly).sin();
+    let obliquity = (23.439 - 0.000_000_4 * days).to_radians();
+
+    let right_ascension =
+        (obliquity.cos() * ecliptic_longitude.sin()).atan2(ecliptic_longitude.cos());
+    let declination = (obliquity.sin() * ecliptic_longitude.sin()).asin();
+    let sidereal_time = (280.460_618_37 + 360.985_647_366_29 * days).to_radians();
+    let hour_angle = sidereal_time + coordinates.lon().to_radians() - right_ascension;
+
+    let latitude = coordinates.lat().to_radians();
+    (latitude.sin() * declination.sin() + latitude.cos() * declination.cos() * hour_angle.cos())
+        .asin()
+        .to_degrees()
+}
+
+struct ElevationScheduler {
+    coordinates: Coordinates,
+    day_elevation: f64,
+    night_elevation: f64,
+}
+
+impl ElevationScheduler {
+    /// Returns how far the sun is from the night elevation to the day elevation, from 0.0 to 1.0
+    fn get(&self, date_time: DateTime<chrono::Utc>) -> f64 {
+        let elevation = solar_elevation(self.coordinates, date_time);
+        ((elevation - self.night_elevation) / (self.day_elevation - self.night_elevation))
+            .clamp(0.0, 1.0)
+    }
+}
+
+enum SchedulerKind {
+    /// Schedulers for `[day, night, periods...]`
+    Event(Vec<Box<dyn Scheduler>>),
+    Elevation(ElevationScheduler),
+}
+
+/// A mode switch on the upcoming schedule
+pub struct Switch {
+    pub date_time: DateTime<chrono::Utc>,
+    pub mode: ColorMode,
+    /// Where the time comes from, e.g. `auto (sunset)`
+    pub source: String,
+}
+
 pub struct ModeScheduler {
     pub mode: ColorMode,
+    /// Mode the transition into `mode` starts from
+    pub previous_mode: ColorMode,
+    /// Fraction of the way from the previous mode's color to the current one, from 0.0 to 1.0
+    pub progress: f64,
+    /// Time until the color has to be recomputed
     pub delay_ms: i64,
-    day_scheduler: Box<dyn Scheduler>,
-    night_scheduler: Box<dyn Scheduler>,
+    /// Time until the next mode switch, unknown when following the sun's elevation
+    pub switch_ms: Option<i64>,
+    kind: SchedulerKind,
+    transition_ms: i64,
+    polar_fallback: PolarFallback,
+    /// Whether today is following `polar_fallback`, kept to log changes
+    polar: Option<Polar>,
+    zone: Zone,
+    clock: Rc<dyn Clock>,
 }
 
 impl ModeScheduler {
     pub fn new(schedule: Schedule, location: Option<Location>) -> anyhow::Result<Self> {
+        Self::with_clock(schedule, location, Rc::new(SystemClock))
+    }
+
+    /// Creates a scheduler that reads the current time from `clock`
+    pub fn with_clock(
+        schedule: Schedule,
+        location: Option<Location>,
+        clock: Rc<dyn Clock>,
+    ) -> anyhow::Result<Self> {
+        for (name, skipped, resolved) in
+            skipped_times(&schedule, schedule.zone.date(clock.now()), CHECKED_DAYS)
+        {
+            log::warn!(
+                "`{}` ({}) does not exist as the clocks go forward, it is moved to {}",
+                name,
+                skipped.format("%Y-%m-%d %H:%M"),
+                resolved.format("%H:%M")
+            );
+        }
+
         let coordinates = match (&schedule.day, &schedule.night) {
-            (ScheduleType::Fixed(_), ScheduleType::Fixed(_)) => None,
+            (ScheduleType::Fixed(_), ScheduleType::Fixed(_))
+                if schedule
+                    .periods
+                    .iter()
+                    .all(|p| matches!(p, ScheduleType::Fixed(_)))
+                    && schedule.weekdays.iter().flatten().all(|weekday| {
+                        matches!(
+                            (weekday.day, weekday.night),
+                            (ScheduleType::Fixed(_), ScheduleType::Fixed(_))
+                        )
+                    })
+                    && schedule.exceptions.iter().all(|exception| {
+                        [exception.day, exception.night]
+                            .iter()
+                            .flatten()
+                            .all(|p| matches!(p, ScheduleType::Fixed(_)))
+                    }) =>
+            {
+                None
+            }
             _ => {
                 let location = location.ok_or(InternalError {
                     message: "Location is required",
@@ -106,7 +363,10 @@ impl ModeScheduler {
                     coordinates: coordinates.ok_or(error)?,
                     event_type,
                 }),
-                ScheduleType::Fixed(naive_time) => Box::new(FixedScheduler { naive_time }),
+                ScheduleType::Fixed(naive_time) => Box::new(FixedScheduler {
+                    naive_time,
+                    zone: schedule.zone.clone(),
+                }),
                 ScheduleType::Relative(time_delta) => Box::new(RelativeScheduler {
                     auto_scheduler: AutoScheduler {
                         coordinates: coordinates.ok_or(error)?,
@@ -114,59 +374,392 @@ impl ModeScheduler {
                     },
                     time_delta,
                 }),
+                // A plain anchor follows the sun like `auto`, only an offset makes it relative
+                ScheduleType::Solar(event_type, time_delta) if time_delta.is_zero() => {
+                    Box::new(AutoScheduler {
+                        coordinates: coordinates.ok_or(error)?,
+                        event_type,
+                    })
+                }
+                ScheduleType::Solar(event_type, time_delta) => Box::new(RelativeScheduler {
+                    auto_scheduler: AutoScheduler {
+                        coordinates: coordinates.ok_or(error)?,
+                        event_type,
+                    },
+                    time_delta,
+                }),
+                ScheduleType::Elevation(_) => Err(InternalError {
+                    message: "Elevation cannot be mixed with other schedule types",
+                })?,
             })
         };
-        let day_scheduler = create_scheduler(schedule.day, Sunrise)?;
-        let night_scheduler = create_scheduler(schedule.night, Sunset)?;
+        let kind = match (schedule.day, schedule.night) {
+            (ScheduleType::Elevation(day_elevation), ScheduleType::Elevation(night_elevation)) => {
+                SchedulerKind::Elevation(ElevationScheduler {
+                    coordinates: coordinates.ok_or(InternalError {
+                        message: "Coordinates are not set",
+                    })?,
+                    day_elevation,
+                    night_elevation,
+                })
+            }
+            (day, night)
+                if schedule.weekdays.iter().all(Option::is_none)
+                    && schedule.exceptions.is_empty() =>
+            {
+                let mut schedulers = vec![
+                    create_scheduler(day, Sunrise)?,
+                    create_scheduler(night, Sunset)?,
+                ];
+                for period in schedule.periods {
+                    schedulers.push(create_scheduler(period, Sunset)?);
+                }
+                SchedulerKind::Event(schedulers)
+            }
+            _ => {
+                let mut day_scheduler = DateScheduler {
+                    exceptions: Vec::new(),
+                    weekdays: Vec::new(),
+                };
+                let mut night_scheduler = DateScheduler {
+                    exceptions: Vec::new(),
+                    weekdays: Vec::new(),
+                };
+                for exception in &schedule.exceptions {
+                    if let Some(day) = exception.day {
+                        day_scheduler
+                            .exceptions
+                            .push((exception.clone(), create_scheduler(day, Sunrise)?));
+                    }
+                    if let Some(night) = exception.night {
+                        night_scheduler
+                            .exceptions
+                            .push((exception.clone(), create_scheduler(night, Sunset)?));
+                    }
+                }
+                for weekday in 0..7 {
+                    let (day, night) = schedule.weekday_day_night(Weekday::try_from(weekday)?);
+                    day_scheduler.weekdays.push(create_scheduler(day, Sunrise)?);
+                    night_scheduler
+                        .weekdays
+                        .push(create_scheduler(night, Sunset)?);
+                }
+                let mut schedulers: Vec<Box<dyn Scheduler>> =
+                    vec![Box::new(day_scheduler), Box::new(night_scheduler)];
+                for period in schedule.periods {
+                    schedulers.push(create_scheduler(period, Sunset)?);
+                }
+                SchedulerKind::Event(schedulers)
+            }
+        };
 
-        let (mode, delay_ms) = get_next_schedule(&*day_scheduler, &*night_scheduler);
+        let mut mode_scheduler = Self {
+            mode: ColorMode::Day,
+            previous_mode: ColorMode::Night,
+            progress: 1.0,
+            delay_ms: 0,
+            switch_ms: None,
+            kind,
+            transition_ms: schedule.transition.num_milliseconds(),
+            polar_fallback: schedule.polar_fallback,
+            polar: None,
+            zone: schedule.zone,
+            clock,
+        };
+        // Only the first date is reported, as the same times usually repeat for a while
+        if let Some((day, night)) = mode_scheduler.day_after_night(CHECKED_DAYS).first() {
+            log::error!(
+                "`schedule.day` ({}) occurs after `schedule.night` ({})",
+                day.format("%Y-%m-%d %H:%M"),
+                night.format("%H:%M"),
+            );
+        }
+        mode_scheduler.next();
+        Ok(mode_scheduler)
+    }
 
-        Ok(Self {
-            mode,
-            delay_ms,
-            day_scheduler,
-            night_scheduler,
-        })
+    pub fn clock(&self) -> &dyn Clock {
+        self.clock.as_ref()
+    }
+
+    /// Returns the mode switches within the next `days` days, `None` when following the sun's
+    /// elevation
+    pub fn timeline(&self, days: i64) -> Option<Vec<Switch>> {
+        let SchedulerKind::Event(schedulers) = &self.kind else {
+            return None;
+        };
+        let now = self.clock.now();
+        let date = self.zone.date(now);
+        let end = now + TimeDelta::days(days);
+
+        let mut switches: Vec<Switch> = (-1..=days + 1)
+            .flat_map(|days| {
+                switches_on(
+                    schedulers,
+                    self.polar_fallback,
+                    &self.zone,
+                    date + TimeDelta::days(days),
+                )
+                .0
+            })
+            .collect();
+        sort_switches(&mut switches);
+        // Polar fallbacks that stay in one mode repeat it every day
+        switches.dedup_by_key(|switch| switch.mode);
+        switches.retain(|switch| switch.date_time > now && switch.date_time <= end);
+        Some(switches)
+    }
+
+    /// Returns the day and night times in the schedule's zone of the dates within the next
+    /// `days` days where day occurs after night, which periods allow for a night after midnight
+    pub fn day_after_night(
+        &self,
+        days: i64,
+    ) -> Vec<(DateTime<FixedOffset>, DateTime<FixedOffset>)> {
+        let SchedulerKind::Event(schedulers) = &self.kind else {
+            return Vec::new();
+        };
+        if schedulers.len() > 2 {
+            return Vec::new();
+        }
+        let local = |date_time: DateTime<chrono::Utc>| {
+            date_time.with_timezone(&self.zone.offset(date_time))
+        };
+        let date = self.zone.date(self.clock.now());
+        (0..days)
+            .filter_map(|days| day_after_night(schedulers, date + TimeDelta::days(days)))
+            .map(|(day, night)| (local(day), local(night)))
+            .collect()
     }
 
     pub fn next(&mut self) {
-        let (mode, delay_ms) = get_next_schedule(&*self.day_scheduler, &*self.night_scheduler);
-        self.mode = mode;
-        self.delay_ms = delay_ms;
+        match &self.kind {
+            SchedulerKind::Event(schedulers) => {
+                let (previous_mode, mode, elapsed_ms, switch_ms, polar) = get_next_schedule(
+                    schedulers,
+                    self.polar_fallback,
+                    &self.zone,
+                    self.clock.now(),
+                );
+                if polar != self.polar {
+                    match polar {
+                        Some(polar) => log::info!(
+                            "A solar event of the schedule does not occur today as the sun stays {} it, {}",
+                            if polar == Polar::Day {
+                                "above"
+                            } else {
+                                "below"
+                            },
+                            describe_polar_fallback(self.polar_fallback, polar)
+                        ),
+                        None => log::info!(
+                            "Every solar event of the schedule occurs again today, resume the schedule"
+                        ),
+                    }
+                    self.polar = polar;
+                }
+                self.mode = mode;
+                self.previous_mode = previous_mode;
+                self.switch_ms = Some(switch_ms);
+                if elapsed_ms < self.transition_ms {
+                    let step_ms =
+                        (self.transition_ms / TRANSITION_STEPS).max(TRANSITION_STEP_MIN_MS);
+                    self.progress = elapsed_ms.max(0) as f64 / self.transition_ms as f64;
+                    self.delay_ms = step_ms.min(self.transition_ms - elapsed_ms).min(switch_ms);
+                } else {
+                    self.progress = 1.0;
+                    self.delay_ms = switch_ms;
+                }
+            }
+            SchedulerKind::Elevation(elevation_scheduler) => {
+                let daylight = elevation_scheduler.get(self.clock.now());
+                if daylight >= 0.5 {
+                    self.mode = ColorMode::Day;
+                    self.previous_mode = ColorMode::Night;
+                    self.progress = daylight;
+                } else {
+                    self.mode = ColorMode::Night;
+                    self.previous_mode = ColorMode::Day;
+                    self.progress = 1.0 - daylight;
+                }
+                self.switch_ms = None;
+                self.delay_ms = if daylight > 0.0 && daylight < 1.0 {
+                    ELEVATION_STEP_MS
+                } else {
+                    ELEVATION_IDLE_MS
+                };
+            }
+        }
     }
 }
 
-fn get_next_schedule(
-    day_scheduler: &dyn Scheduler,
-    night_scheduler: &dyn Scheduler,
-) -> (ColorMode, i64) {
-    let date = Local::now().date_naive();
-    let now = Utc::now();
-
-    let day_date_time = day_scheduler.get(date);
-    let night_date_time = night_scheduler.get(date);
-
-    if day_date_time > night_date_time {
-        log::error!(
-            "`schedule.day` ({}) occurs after `schedule.night` ({})",
-            day_date_time.with_timezone(&Local).format("%H:%M"),
-            night_date_time.with_timezone(&Local).format("%H:%M"),
-        );
+/// Number of days ahead the schedule is checked for skipped times and day after night
+const CHECKED_DAYS: i64 = 366;
+
+/// Returns the fixed times of `schedule` that fall into a gap of its zone within `days` days from
+/// `date`, with the first time they do and the time it is moved to
+fn skipped_times(
+    schedule: &Schedule,
+    date: NaiveDate,
+    days: i64,
+) -> Vec<(String, NaiveDateTime, NaiveDateTime)> {
+    let mut skipped: Vec<(String, NaiveDateTime, NaiveDateTime)> = Vec::new();
+    for date in (0..days).map(|days| date + TimeDelta::days(days)) {
+        let (day, night) = schedule.day_night(date);
+        let (day_key, night_key) = schedule.day_night_keys(date);
+        let mut schedule_types = vec![(day_key, day), (night_key, night)];
+        for (index, period) in schedule.periods.iter().enumerate() {
+            schedule_types.push((format!("period[{}].start", index), *period));
+        }
+        let mut fixed_times: Vec<(String, NaiveTime)> = schedule_types
+            .into_iter()
+            .filter_map(|(name, schedule_type)| match schedule_type {
+                ScheduleType::Fixed(naive_time) => Some((name, naive_time)),
+                _ => None,
+            })
+            .collect();
+        if let PolarFallback::Fixed(day, night) = schedule.polar_fallback {
+            fixed_times.push(("schedule.polar-fallback".to_string(), day));
+            fixed_times.push(("schedule.polar-fallback".to_string(), night));
+        }
+
+        for (name, naive_time) in fixed_times {
+            // Only the first date a time is skipped on is reported
+            if skipped
+                .iter()
+                .any(|(skipped_name, local, _)| *skipped_name == name && local.time() == naive_time)
+            {
+                continue;
+            }
+            let local = NaiveDateTime::new(date, naive_time);
+            let date_time = schedule.zone.resolve(local);
+            let resolved = date_time
+                .with_timezone(&schedule.zone.offset(date_time))
+                .naive_local();
+            if resolved != local {
+                skipped.push((name, local, resolved));
+            }
+        }
     }
+    skipped
+}
 
-    let mode: ColorMode;
-    let until: DateTime<chrono::Utc>;
-    if now < day_date_time {
-        mode = ColorMode::Night;
-        until = day_date_time;
-    } else if now < night_date_time {
-        mode = ColorMode::Day;
-        until = night_date_time;
-    } else {
-        mode = ColorMode::Night;
-        until = day_scheduler.get(date.succ_opt().unwrap());
+/// Returns the day and night times of `date` if day occurs after night
+fn day_after_night(
+    schedulers: &[Box<dyn Scheduler>],
+    date: NaiveDate,
+) -> Option<(DateTime<chrono::Utc>, DateTime<chrono::Utc>)> {
+    let day_date_time = schedulers[0].get(date).ok()?;
+    let night_date_time = schedulers[1].get(date).ok()?;
+    (day_date_time > night_date_time).then_some((day_date_time, night_date_time))
+}
+
+/// Returns the mode switches on `date`, replaced by those of `polar_fallback` if the sun does not
+/// rise or set on it
+fn switches_on(
+    schedulers: &[Box<dyn Scheduler>],
+    polar_fallback: PolarFallback,
+    zone: &Zone,
+    date: NaiveDate,
+) -> (Vec<Switch>, Option<Polar>) {
+    let mut switches = Vec::new();
+    for (index, scheduler) in schedulers.iter().enumerate() {
+        match scheduler.get(date) {
+            Ok(date_time) => switches.push(Switch {
+                date_time,
+                mode: ColorMode::from_index(index),
+                source: scheduler.source(date),
+            }),
+            Err(polar) => {
+                let switch = |naive_time, mode| Switch {
+                    date_time: zone.resolve(NaiveDateTime::new(date, naive_time)),
+                    mode,
+                    source: "polar fallback".to_string(),
+                };
+                let switches = match polar_fallback {
+                    PolarFallback::Sun if polar == Polar::Day => {
+                        vec![switch(NaiveTime::MIN, ColorMode::Day)]
+                    }
+                    PolarFallback::Sun => vec![switch(NaiveTime::MIN, ColorMode::Night)],
+                    PolarFallback::Day => vec![switch(NaiveTime::MIN, ColorMode::Day)],
+                    PolarFallback::Night => vec![switch(NaiveTime::MIN, ColorMode::Night)],
+                    PolarFallback::Fixed(day, night) => {
+                        vec![switch(day, ColorMode::Day), switch(night, ColorMode::Night)]
+                    }
+                };
+                return (switches, Some(polar));
+            }
+        }
     }
-    (mode, (until - now).num_milliseconds() + 1)
+    (switches, None)
+}
+
+/// Sorts switches by time, dropping those replaced by another one at the same time
+///
+/// Events the sun does not reach can share their substitute time, e.g. a dawn and the dusk before
+/// it both at solar midnight, the later one on the schedule wins.
+fn sort_switches(switches: &mut Vec<Switch>) {
+    switches.sort_by_key(|switch| switch.date_time);
+    switches.reverse();
+    switches.dedup_by_key(|switch| switch.date_time);
+    switches.reverse();
+}
+
+fn describe_polar_fallback(polar_fallback: PolarFallback, polar: Polar) -> String {
+    match polar_fallback {
+        PolarFallback::Sun if polar == Polar::Day => "stay in [day] mode".to_string(),
+        PolarFallback::Sun => "stay in [night] mode".to_string(),
+        PolarFallback::Day => "stay in [day] mode".to_string(),
+        PolarFallback::Night => "stay in [night] mode".to_string(),
+        PolarFallback::Fixed(day, night) => format!(
+            "switch to [day] mode at {} and [night] mode at {}",
+            day.format("%H:%M"),
+            night.format("%H:%M")
+        ),
+    }
+}
+
+/// Returns the previous and current modes, milliseconds since the current mode started,
+/// milliseconds until it ends and whether today follows the polar fallback
+fn get_next_schedule(
+    schedulers: &[Box<dyn Scheduler>],
+    polar_fallback: PolarFallback,
+    zone: &Zone,
+    now: DateTime<chrono::Utc>,
+) -> (ColorMode, ColorMode, i64, i64, Option<Polar>) {
+    let date = zone.date(now);
+
+    // Offsets are shorter than a day, so two days on either side always surround `now`
+    let mut polar = None;
+    let mut switches: Vec<Switch> = Vec::new();
+    for days in -2..=2 {
+        let (date_switches, date_polar) = switches_on(
+            schedulers,
+            polar_fallback,
+            zone,
+            date + TimeDelta::days(days),
+        );
+        if days == 0 {
+            polar = date_polar;
+        }
+        switches.extend(date_switches);
+    }
+    sort_switches(&mut switches);
+
+    let next = switches
+        .iter()
+        .position(|switch| switch.date_time > now)
+        .unwrap();
+    let since = &switches[next - 1];
+    let previous = &switches[next - 2];
+    let until = &switches[next];
+    (
+        previous.mode,
+        since.mode,
+        (now - since.date_time).num_milliseconds(),
+        (until.date_time - now).num_milliseconds() + 1,
+        polar,
+    )
 }
 
 #[cfg(test)]
@@ -174,8 +767,10 @@ mod test {
     use chrono::{
         FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeDelta, TimeZone, Timelike,
     };
+    use sunrise::DawnType;
 
     use super::*;
+    use crate::{clock::mock_chrono, config::WeekdaySchedule};
 
     const HOUR: i32 = 3600;
     const NAIVEDATE: NaiveDate = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
@@ -245,6 +840,12 @@ mod test {
             const DAY_NIGHT_TIME: Schedule = Schedule {
                 day: ScheduleType::Auto,
                 night: ScheduleType::Auto,
+                periods: Vec::new(),
+                transition: TimeDelta::zero(),
+                polar_fallback: PolarFallback::Sun,
+                zone: Zone::Local,
+                weekdays: [None; 7],
+                exceptions: Vec::new(),
             };
             const SUNRISE: u32 = 6;
             const SUNSET: u32 = 18;
@@ -288,6 +889,12 @@ mod test {
             const DAY_NIGHT_TIME: Schedule = Schedule {
                 day: ScheduleType::Fixed(NaiveTime::from_hms_opt(8, 0, 0).unwrap()),
                 night: ScheduleType::Fixed(NaiveTime::from_hms_opt(19, 0, 0).unwrap()),
+                periods: Vec::new(),
+                transition: TimeDelta::zero(),
+                polar_fallback: PolarFallback::Sun,
+                zone: Zone::Local,
+                weekdays: [None; 7],
+                exceptions: Vec::new(),
             };
             const SUNRISE: u32 = 8;
             const SUNSET: u32 = 19;
@@ -319,6 +926,211 @@ mod test {
                 assert_next_event(&mut event, ColorMode::Night, SUNRISE, RANGE, OFFSET);
                 assert_next_event(&mut event, ColorMode::Day, SUNSET, RANGE, OFFSET);
             }
+
+            #[test]
+            fn named_zone() {
+                set_time(0, 0, *OFFSET);
+                let schedule = Schedule {
+                    zone: Zone::named("Asia/Tokyo").unwrap(),
+                    ..DAY_NIGHT_TIME
+                };
+                let mut event = ModeScheduler::new(schedule, LOCATION).unwrap();
+
+                // 08:00 and 19:00 in Tokyo are 23:00 and 10:00 in UTC
+                assert_next_event(&mut event, ColorMode::Day, 10, RANGE, OFFSET);
+                assert_next_event(&mut event, ColorMode::Night, 23, RANGE, OFFSET);
+            }
+
+            #[test]
+            fn weekdays() {
+                // 2000-01-01 is a Saturday
+                set_time(0, 0, *OFFSET);
+                let weekend = Some(WeekdaySchedule {
+                    day: ScheduleType::Fixed(NaiveTime::from_hms_opt(10, 0, 0).unwrap()),
+                    night: ScheduleType::Fixed(NaiveTime::from_hms_opt(19, 0, 0).unwrap()),
+                    day_table: "schedule.weekend",
+                    night_table: "schedule.weekend",
+                });
+                let monday = Some(WeekdaySchedule {
+                    day: ScheduleType::Fixed(NaiveTime::from_hms_opt(8, 0, 0).unwrap()),
+                    night: ScheduleType::Fixed(NaiveTime::from_hms_opt(22, 0, 0).unwrap()),
+                    day_table: "schedule.mon",
+                    night_table: "schedule.mon",
+                });
+                let schedule = Schedule {
+                    weekdays: [monday, None, None, None, None, weekend, weekend],
+                    ..DAY_NIGHT_TIME
+                };
+                let event = ModeScheduler::new(schedule, LOCATION).unwrap();
+
+                assert_eq!(event.mode, ColorMode::Night);
+                let switches = event.timeline(3).unwrap();
+                assert_eq!(
+                    switches
+                        .iter()
+                        .map(|switch| (switch.mode, switch.date_time.hour()))
+                        .collect::<Vec<_>>(),
+                    [
+                        (ColorMode::Day, 10),
+                        (ColorMode::Night, 19),
+                        (ColorMode::Day, 10),
+                        (ColorMode::Night, 19),
+                        (ColorMode::Day, 8),
+                        (ColorMode::Night, 22),
+                    ]
+                );
+                assert_eq!(switches[0].source, "fixed on Saturday");
+            }
+
+            #[test]
+            fn exceptions() {
+                // 2000-01-01 is a Saturday
+                set_time(0, 0, *OFFSET);
+                let schedule = Schedule {
+                    exceptions: vec![Exception {
+                        dates: vec![(NAIVEDATE, NAIVEDATE + TimeDelta::days(1))],
+                        day: None,
+                        night: Some(ScheduleType::Fixed(
+                            NaiveTime::from_hms_opt(21, 0, 0).unwrap(),
+                        )),
+                    }],
+                    ..DAY_NIGHT_TIME
+                };
+                let event = ModeScheduler::new(schedule, LOCATION).unwrap();
+
+                let switches = event.timeline(3).unwrap();
+                assert_eq!(
+                    switches
+                        .iter()
+                        .map(|switch| (switch.mode, switch.date_time.hour()))
+                        .collect::<Vec<_>>(),
+                    [
+                        (ColorMode::Day, 8),
+                        (ColorMode::Night, 21),
+                        (ColorMode::Day, 8),
+                        (ColorMode::Night, 21),
+                        (ColorMode::Day, 8),
+                        (ColorMode::Night, 19),
+                    ]
+                );
+                assert_eq!(switches[0].source, "fixed on Saturday");
+                assert_eq!(switches[1].source, "fixed on exception");
+            }
+        }
+
+        mod dst {
+            use super::*;
+
+            const UTC: &FixedOffset = &FixedOffset::east_opt(0).unwrap();
+            const CET: FixedOffset = FixedOffset::east_opt(HOUR).unwrap();
+            const CEST: FixedOffset = FixedOffset::east_opt(2 * HOUR).unwrap();
+            const RANGE: Range<u32> = 30..31;
+
+            const DAY_NIGHT_TIME: Schedule = Schedule {
+                day: ScheduleType::Fixed(NaiveTime::from_hms_opt(8, 0, 0).unwrap()),
+                night: ScheduleType::Fixed(NaiveTime::from_hms_opt(2, 30, 0).unwrap()),
+                periods: Vec::new(),
+                transition: TimeDelta::zero(),
+                polar_fallback: PolarFallback::Sun,
+                zone: Zone::Local,
+                weekdays: [None; 7],
+                exceptions: Vec::new(),
+            };
+
+            fn set_local(offset: FixedOffset, month: u32, day: u32, hour: u32) {
+                mock_chrono::set(
+                    offset
+                        .with_ymd_and_hms(2000, month, day, hour, 0, 0)
+                        .unwrap(),
+                );
+            }
+
+            #[test]
+            fn spring_forward() {
+                // Clocks go forward from 02:00 to 03:00, skipping 02:30
+                set_local(CET, 3, 25, 12);
+                mock_chrono::set_transition(
+                    UTC.with_ymd_and_hms(2000, 3, 26, 1, 0, 0).unwrap().to_utc(),
+                    CEST,
+                );
+                assert_eq!(
+                    skipped_times(
+                        &DAY_NIGHT_TIME,
+                        NaiveDate::from_ymd_opt(2000, 3, 1).unwrap(),
+                        60
+                    ),
+                    vec![(
+                        "schedule.night".to_string(),
+                        NaiveDateTime::new(
+                            NaiveDate::from_ymd_opt(2000, 3, 26).unwrap(),
+                            NaiveTime::from_hms_opt(2, 30, 0).unwrap()
+                        ),
+                        NaiveDateTime::new(
+                            NaiveDate::from_ymd_opt(2000, 3, 26).unwrap(),
+                            NaiveTime::from_hms_opt(3, 30, 0).unwrap()
+                        )
+                    )]
+                );
+
+                let mut event = ModeScheduler::new(DAY_NIGHT_TIME, None).unwrap();
+
+                // 02:30 is moved to 03:30 CEST
+                assert_next_event(&mut event, ColorMode::Day, 1, RANGE, UTC);
+            }
+
+            #[test]
+            fn spring_forward_override() {
+                set_local(CET, 3, 25, 12);
+                mock_chrono::set_transition(
+                    UTC.with_ymd_and_hms(2000, 3, 26, 1, 0, 0).unwrap().to_utc(),
+                    CEST,
+                );
+                let date = NaiveDate::from_ymd_opt(2000, 3, 26).unwrap();
+                let schedule = Schedule {
+                    night: ScheduleType::Fixed(NaiveTime::from_hms_opt(23, 0, 0).unwrap()),
+                    exceptions: vec![Exception {
+                        dates: vec![(date, date)],
+                        day: None,
+                        night: Some(ScheduleType::Fixed(
+                            NaiveTime::from_hms_opt(2, 15, 0).unwrap(),
+                        )),
+                    }],
+                    ..DAY_NIGHT_TIME
+                };
+                assert_eq!(
+                    skipped_times(&schedule, date - TimeDelta::days(1), 3),
+                    vec![(
+                        "schedule.exception[0].night".to_string(),
+                        NaiveDateTime::new(date, NaiveTime::from_hms_opt(2, 15, 0).unwrap()),
+                        NaiveDateTime::new(date, NaiveTime::from_hms_opt(3, 15, 0).unwrap())
+                    )]
+                );
+            }
+
+            #[test]
+            fn fall_back() {
+                // Clocks go back from 03:00 to 02:00, repeating 02:30
+                set_local(CEST, 10, 28, 12);
+                mock_chrono::set_transition(
+                    UTC.with_ymd_and_hms(2000, 10, 29, 1, 0, 0)
+                        .unwrap()
+                        .to_utc(),
+                    CET,
+                );
+                assert!(
+                    skipped_times(
+                        &DAY_NIGHT_TIME,
+                        NaiveDate::from_ymd_opt(2000, 10, 1).unwrap(),
+                        60
+                    )
+                    .is_empty()
+                );
+
+                let mut event = ModeScheduler::new(DAY_NIGHT_TIME, None).unwrap();
+
+                // The first 02:30 is taken, which is still in CEST
+                assert_next_event(&mut event, ColorMode::Day, 0, RANGE, UTC);
+            }
         }
 
         mod relative {
@@ -327,6 +1139,12 @@ mod test {
             const DAY_NIGHT_TIME: Schedule = Schedule {
                 day: ScheduleType::Relative(TimeDelta::hours(1)),
                 night: ScheduleType::Relative(TimeDelta::hours(-2)),
+                periods: Vec::new(),
+                transition: TimeDelta::zero(),
+                polar_fallback: PolarFallback::Sun,
+                zone: Zone::Local,
+                weekdays: [None; 7],
+                exceptions: Vec::new(),
             };
             const SUNRISE: u32 = 7;
             const SUNSET: u32 = 16;
@@ -361,135 +1179,543 @@ mod test {
             }
         }
 
-        mod auto_fixed {
+        mod transition {
             use super::*;
 
             const OFFSET: FixedOffset = NAIROBI_OFFSET;
+            const DAY_NIGHT_TIME: Schedule = Schedule {
+                day: ScheduleType::Fixed(NaiveTime::from_hms_opt(8, 0, 0).unwrap()),
+                night: ScheduleType::Fixed(NaiveTime::from_hms_opt(19, 0, 0).unwrap()),
+                periods: Vec::new(),
+                transition: TimeDelta::hours(1),
+                polar_fallback: PolarFallback::Sun,
+                zone: Zone::Local,
+                weekdays: [None; 7],
+                exceptions: Vec::new(),
+            };
 
             #[test]
-            fn day_auto_night_fixed() {
+            fn during_transition() {
+                set_time(8, 15, OFFSET);
+                let event = ModeScheduler::new(DAY_NIGHT_TIME, None).unwrap();
+
+                assert_eq!(event.mode, ColorMode::Day);
+                assert!((event.progress - 0.25).abs() < 1e-6);
+                assert_eq!(event.delay_ms, 12 * 1000);
+            }
+
+            #[test]
+            fn after_transition() {
+                set_time(9, 30, OFFSET);
+                let event = ModeScheduler::new(DAY_NIGHT_TIME, None).unwrap();
+
+                assert_eq!(event.mode, ColorMode::Day);
+                assert_eq!(event.progress, 1.0);
+                assert_eq!(event.delay_ms, event.switch_ms.unwrap());
+            }
+
+            #[test]
+            fn across_midnight() {
+                set_time(19, 30, OFFSET);
+                let event = ModeScheduler::new(
+                    Schedule {
+                        night: ScheduleType::Fixed(NaiveTime::from_hms_opt(23, 30, 0).unwrap()),
+                        ..DAY_NIGHT_TIME
+                    },
+                    None,
+                )
+                .unwrap();
+                assert_eq!(event.mode, ColorMode::Day);
+
                 set_time(0, 0, OFFSET);
-                let mut event = ModeScheduler::new(
+                let event = ModeScheduler::new(
                     Schedule {
-                        day: ScheduleType::Auto,
-                        night: ScheduleType::Fixed(NaiveTime::from_hms_opt(19, 0, 0).unwrap()),
+                        night: ScheduleType::Fixed(NaiveTime::from_hms_opt(23, 30, 0).unwrap()),
+                        ..DAY_NIGHT_TIME
                     },
-                    NAIROBI_LOCATION,
+                    None,
                 )
                 .unwrap();
+                assert_eq!(event.mode, ColorMode::Night);
+                assert!((event.progress - 0.5).abs() < 1e-6);
+            }
+        }
 
-                assert_next_event(&mut event, ColorMode::Night, 6, 15..45, &OFFSET);
-                assert_next_event(&mut event, ColorMode::Day, 19, 0..1, &OFFSET);
+        mod elevation {
+            use super::*;
+
+            const OFFSET: FixedOffset = NAIROBI_OFFSET;
+            const DAY_NIGHT_ELEVATION: Schedule = Schedule {
+                day: ScheduleType::Elevation(3.0),
+                night: ScheduleType::Elevation(-6.0),
+                periods: Vec::new(),
+                transition: TimeDelta::zero(),
+                polar_fallback: PolarFallback::Sun,
+                zone: Zone::Local,
+                weekdays: [None; 7],
+                exceptions: Vec::new(),
+            };
+
+            #[test]
+            fn solar_position() {
+                let coordinates = Coordinates::new(-1.2, 36.8).unwrap();
+                set_time(12, 30, OFFSET);
+                assert!(solar_elevation(coordinates, mock_chrono::Utc::now()) > 60.0);
+                set_time(0, 0, OFFSET);
+                assert!(solar_elevation(coordinates, mock_chrono::Utc::now()) < -60.0);
+                set_time(6, 30, OFFSET);
+                assert!(solar_elevation(coordinates, mock_chrono::Utc::now()).abs() < 5.0);
             }
 
             #[test]
-            fn day_fixed_night_auto() {
+            fn noon() {
+                set_time(12, 0, OFFSET);
+                let event = ModeScheduler::new(DAY_NIGHT_ELEVATION, NAIROBI_LOCATION).unwrap();
+
+                assert_eq!(event.mode, ColorMode::Day);
+                assert_eq!(event.progress, 1.0);
+                assert_eq!(event.switch_ms, None);
+                assert_eq!(event.delay_ms, ELEVATION_IDLE_MS);
+            }
+
+            #[test]
+            fn midnight() {
                 set_time(0, 0, OFFSET);
+                let event = ModeScheduler::new(DAY_NIGHT_ELEVATION, NAIROBI_LOCATION).unwrap();
+
+                assert_eq!(event.mode, ColorMode::Night);
+                assert_eq!(event.progress, 1.0);
+            }
+
+            #[test]
+            fn dawn() {
+                set_time(6, 20, OFFSET);
+                let event = ModeScheduler::new(DAY_NIGHT_ELEVATION, NAIROBI_LOCATION).unwrap();
+
+                assert!(event.progress > 0.5 && event.progress < 1.0);
+                assert_eq!(event.delay_ms, ELEVATION_STEP_MS);
+            }
+        }
+
+        mod twilight {
+            use super::*;
+
+            const DAY_NIGHT_TIME: Schedule = Schedule {
+                day: ScheduleType::Solar(SolarEvent::Dawn(DawnType::Civil), TimeDelta::zero()),
+                night: ScheduleType::Solar(
+                    SolarEvent::Dusk(DawnType::Astronomical),
+                    TimeDelta::minutes(30),
+                ),
+                periods: Vec::new(),
+                transition: TimeDelta::zero(),
+                polar_fallback: PolarFallback::Sun,
+                zone: Zone::Local,
+                weekdays: [None; 7],
+                exceptions: Vec::new(),
+            };
+            const OFFSET: &FixedOffset = &NAIROBI_OFFSET;
+
+            #[test]
+            fn morning() {
+                set_time(0, 0, NAIROBI_OFFSET);
+                let mut event = ModeScheduler::new(DAY_NIGHT_TIME, NAIROBI_LOCATION).unwrap();
+
+                assert_next_event(&mut event, ColorMode::Night, 6, 0..30, OFFSET);
+                assert_next_event(&mut event, ColorMode::Day, 20, 15..45, OFFSET);
+            }
+
+            #[test]
+            fn noon() {
+                set_time(13, 0, NAIROBI_OFFSET);
+                let mut event = ModeScheduler::new(DAY_NIGHT_TIME, NAIROBI_LOCATION).unwrap();
+
+                assert_next_event(&mut event, ColorMode::Day, 20, 15..45, OFFSET);
+                assert_next_event(&mut event, ColorMode::Night, 6, 0..30, OFFSET);
+            }
+        }
+
+        mod period {
+            use super::*;
+
+            const OFFSET: &FixedOffset = &NAIROBI_OFFSET;
+
+            fn schedule() -> Schedule {
+                Schedule {
+                    day: ScheduleType::Fixed(NaiveTime::from_hms_opt(8, 0, 0).unwrap()),
+                    night: ScheduleType::Fixed(NaiveTime::from_hms_opt(0, 30, 0).unwrap()),
+                    periods: vec![
+                        ScheduleType::Solar(SolarEvent::Sunset, TimeDelta::zero()),
+                        ScheduleType::Fixed(NaiveTime::from_hms_opt(23, 0, 0).unwrap()),
+                    ],
+                    transition: TimeDelta::zero(),
+                    polar_fallback: PolarFallback::Sun,
+                    zone: Zone::Local,
+                    weekdays: [None; 7],
+                    exceptions: Vec::new(),
+                }
+            }
+
+            #[test]
+            fn noon() {
+                set_time(13, 0, NAIROBI_OFFSET);
+                let mut event = ModeScheduler::new(schedule(), NAIROBI_LOCATION).unwrap();
+
+                assert_next_event(&mut event, ColorMode::Day, 18, 15..45, OFFSET);
+                assert_next_event(&mut event, ColorMode::Period(0), 23, 0..1, OFFSET);
+                assert_eq!(event.previous_mode, ColorMode::Period(0));
+                assert_next_event(&mut event, ColorMode::Period(1), 0, 30..31, OFFSET);
+                assert_next_event(&mut event, ColorMode::Night, 8, 0..1, OFFSET);
+                assert_eq!(event.mode, ColorMode::Day);
+                assert_eq!(event.previous_mode, ColorMode::Night);
+            }
+
+            #[test]
+            fn after_midnight() {
+                set_time(0, 15, NAIROBI_OFFSET);
+                let mut event = ModeScheduler::new(schedule(), NAIROBI_LOCATION).unwrap();
+
+                assert_eq!(event.previous_mode, ColorMode::Period(0));
+                assert_next_event(&mut event, ColorMode::Period(1), 0, 30..31, OFFSET);
+                assert_next_event(&mut event, ColorMode::Night, 8, 0..1, OFFSET);
+            }
+
+            #[test]
+            fn relative_start() {
+                set_time(13, 0, NAIROBI_OFFSET);
+                let schedule = Schedule {
+                    periods: vec![ScheduleType::Relative(TimeDelta::hours(1))],
+                    ..schedule()
+                };
+                let mut event = ModeScheduler::new(schedule, NAIROBI_LOCATION).unwrap();
+
+                // One hour after sunset
+                assert_next_event(&mut event, ColorMode::Day, 19, 15..45, OFFSET);
+                assert_next_event(&mut event, ColorMode::Period(0), 0, 30..31, OFFSET);
+                assert_eq!(event.mode, ColorMode::Night);
+            }
+        }
+
+        mod polar {
+            use super::*;
+
+            const LONGYEARBYEN_OFFSET: FixedOffset = FixedOffset::east_opt(HOUR).unwrap();
+            const LONGYEARBYEN_LOCATION: Option<Location> = Some(Location {
+                latitude: 78.2,
+                longitude: 15.6,
+            });
+
+            fn set_date(month: u32, day: u32, hour: u32) {
+                mock_chrono::set(
+                    LONGYEARBYEN_OFFSET
+                        .with_ymd_and_hms(2000, month, day, hour, 0, 0)
+                        .unwrap(),
+                );
+            }
+
+            fn schedule(polar_fallback: PolarFallback) -> Schedule {
+                Schedule {
+                    day: ScheduleType::Auto,
+                    night: ScheduleType::Auto,
+                    periods: Vec::new(),
+                    transition: TimeDelta::zero(),
+                    polar_fallback,
+                    zone: Zone::Local,
+                    weekdays: [None; 7],
+                    exceptions: Vec::new(),
+                }
+            }
+
+            #[test]
+            fn midnight_sun() {
+                set_date(6, 21, 1);
+                let event = ModeScheduler::new(schedule(PolarFallback::Sun), LONGYEARBYEN_LOCATION)
+                    .unwrap();
+
+                assert_eq!(event.mode, ColorMode::Day);
+                assert_eq!(event.polar, Some(Polar::Day));
+                assert!(event.timeline(7).unwrap().is_empty());
+            }
+
+            #[test]
+            fn polar_night() {
+                set_date(12, 21, 13);
+                let event = ModeScheduler::new(schedule(PolarFallback::Sun), LONGYEARBYEN_LOCATION)
+                    .unwrap();
+
+                assert_eq!(event.mode, ColorMode::Night);
+                assert_eq!(event.polar, Some(Polar::Night));
+            }
+
+            #[test]
+            fn stay_in_mode() {
+                set_date(12, 21, 13);
+                let event = ModeScheduler::new(schedule(PolarFallback::Day), LONGYEARBYEN_LOCATION)
+                    .unwrap();
+                assert_eq!(event.mode, ColorMode::Day);
+
+                set_date(6, 21, 13);
+                let event =
+                    ModeScheduler::new(schedule(PolarFallback::Night), LONGYEARBYEN_LOCATION)
+                        .unwrap();
+                assert_eq!(event.mode, ColorMode::Night);
+            }
+
+            #[test]
+            fn fixed_times() {
+                set_date(12, 21, 13);
+                let mut event = ModeScheduler::new(
+                    schedule(PolarFallback::Fixed(
+                        NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                        NaiveTime::from_hms_opt(20, 0, 0).unwrap(),
+                    )),
+                    LONGYEARBYEN_LOCATION,
+                )
+                .unwrap();
+
+                assert_next_event(&mut event, ColorMode::Day, 20, 0..1, &LONGYEARBYEN_OFFSET);
+                assert_next_event(&mut event, ColorMode::Night, 8, 0..1, &LONGYEARBYEN_OFFSET);
+            }
+
+            #[test]
+            fn twilight_at_mid_latitude() {
+                const LONDON_LOCATION: Option<Location> = Some(Location {
+                    latitude: 51.5,
+                    longitude: -0.1,
+                });
+                // Astronomical dusk does not occur in London around the June solstice
+                let utc = FixedOffset::east_opt(0).unwrap();
+                mock_chrono::set(utc.with_ymd_and_hms(2000, 6, 21, 12, 0, 0).unwrap());
                 let mut event = ModeScheduler::new(
                     Schedule {
-                        day: ScheduleType::Fixed(NaiveTime::from_hms_opt(7, 0, 0).unwrap()),
-                        night: ScheduleType::Auto,
+                        night: ScheduleType::Solar(
+                            SolarEvent::Dusk(DawnType::Astronomical),
+                            TimeDelta::zero(),
+                        ),
+                        periods: vec![ScheduleType::Solar(
+                            SolarEvent::Dusk(DawnType::Civil),
+                            TimeDelta::zero(),
+                        )],
+                        ..schedule(PolarFallback::Sun)
                     },
-                    NAIROBI_LOCATION,
+                    LONDON_LOCATION,
                 )
                 .unwrap();
+                assert_eq!(event.mode, ColorMode::Day);
+                assert_eq!(event.polar, None);
+
+                let switches = event.timeline(1).unwrap();
+                assert_eq!(
+                    switches
+                        .iter()
+                        .map(|switch| (switch.mode, switch.source.as_str()))
+                        .collect::<Vec<_>>(),
+                    [
+                        (ColorMode::Period(0), "auto (civil-dusk)"),
+                        (
+                            ColorMode::Night,
+                            "auto (astronomical-dusk does not occur, solar midnight)"
+                        ),
+                        (ColorMode::Day, "auto (sunrise)"),
+                    ]
+                );
+
+                assert_next_event(&mut event, ColorMode::Day, 21, 0..20, &utc);
+                assert_next_event(&mut event, ColorMode::Period(0), 0, 0..2, &utc);
+                assert_next_event(&mut event, ColorMode::Night, 3, 40..50, &utc);
+                assert_eq!(event.mode, ColorMode::Day);
+                assert_eq!(event.polar, None);
+            }
 
-                assert_next_event(&mut event, ColorMode::Night, 7, 0..1, &OFFSET);
-                assert_next_event(&mut event, ColorMode::Day, 18, 15..45, &OFFSET);
+            #[test]
+            fn regular_days() {
+                set_date(3, 21, 13);
+                let event = ModeScheduler::new(schedule(PolarFallback::Sun), LONGYEARBYEN_LOCATION)
+                    .unwrap();
+
+                assert_eq!(event.mode, ColorMode::Day);
+                assert_eq!(event.polar, None);
             }
         }
-    }
-}
 
-#[cfg(test)]
-mod mock_chrono {
-    use std::cell::Cell;
+        mod timeline {
+            use super::*;
 
-    use chrono::{
-        DateTime, FixedOffset, MappedLocalTime, NaiveDate, NaiveDateTime, NaiveTime, Offset,
-        TimeZone,
-    };
+            #[test]
+            fn upcoming_switches() {
+                set_time(13, 0, NAIROBI_OFFSET);
+                let event = ModeScheduler::new(
+                    Schedule {
+                        day: ScheduleType::Fixed(NaiveTime::from_hms_opt(8, 0, 0).unwrap()),
+                        night: ScheduleType::Relative(TimeDelta::minutes(30)),
+                        periods: vec![ScheduleType::Auto],
+                        transition: TimeDelta::zero(),
+                        polar_fallback: PolarFallback::Sun,
+                        zone: Zone::Local,
+                        weekdays: [None; 7],
+                        exceptions: Vec::new(),
+                    },
+                    NAIROBI_LOCATION,
+                )
+                .unwrap();
 
-    thread_local! {
-        static DATE: Cell<Option<DateTime<chrono::FixedOffset>>> = const { Cell::new(None) };
-    }
+                let switches = event.timeline(2).unwrap();
+                assert_eq!(switches.len(), 6);
+                assert_eq!(
+                    switches
+                        .iter()
+                        .map(|switch| (switch.mode, switch.source.as_str()))
+                        .collect::<Vec<_>>()[..3],
+                    [
+                        (ColorMode::Period(0), "auto (sunset)"),
+                        (ColorMode::Night, "relative (sunset +00:30)"),
+                        (ColorMode::Day, "fixed"),
+                    ]
+                );
+                let first = switches[0].date_time.with_timezone(&NAIROBI_OFFSET);
+                assert_eq!(first.hour(), 18);
+                assert_eq!(first.date_naive(), NAIVEDATE);
+                assert!(event.day_after_night(2).is_empty());
+            }
 
-    #[derive(Clone)]
-    pub struct Utc;
+            #[test]
+            fn solar_anchors() {
+                set_time(13, 0, NAIROBI_OFFSET);
+                let event = ModeScheduler::new(
+                    Schedule {
+                        day: ScheduleType::Solar(
+                            SolarEvent::Dawn(DawnType::Civil),
+                            TimeDelta::zero(),
+                        ),
+                        night: ScheduleType::Solar(SolarEvent::Sunset, TimeDelta::hours(1)),
+                        periods: Vec::new(),
+                        transition: TimeDelta::zero(),
+                        polar_fallback: PolarFallback::Sun,
+                        zone: Zone::Local,
+                        weekdays: [None; 7],
+                        exceptions: Vec::new(),
+                    },
+                    NAIROBI_LOCATION,
+                )
+                .unwrap();
 
-    impl Utc {
-        pub fn now() -> DateTime<chrono::Utc> {
-            DATE.with(|date| date.get().unwrap().with_timezone(&chrono::Utc))
-        }
-    }
+                let switches = event.timeline(1).unwrap();
+                assert_eq!(
+                    switches
+                        .iter()
+                        .map(|switch| switch.source.as_str())
+                        .collect::<Vec<_>>(),
+                    ["relative (sunset +01:00)", "auto (civil-dawn)"]
+                );
+            }
 
-    mod inner {
-        use super::*;
+            #[test]
+            fn day_after_night() {
+                set_time(13, 0, NAIROBI_OFFSET);
+                let event = ModeScheduler::new(
+                    Schedule {
+                        day: ScheduleType::Fixed(NaiveTime::from_hms_opt(21, 0, 0).unwrap()),
+                        night: ScheduleType::Auto,
+                        periods: Vec::new(),
+                        transition: TimeDelta::zero(),
+                        polar_fallback: PolarFallback::Sun,
+                        zone: Zone::Local,
+                        weekdays: [None; 7],
+                        exceptions: Vec::new(),
+                    },
+                    NAIROBI_LOCATION,
+                )
+                .unwrap();
 
-        pub(super) fn offset_from_utc_datetime(
-            _utc_time: &NaiveDateTime,
-        ) -> MappedLocalTime<FixedOffset> {
-            DATE.with(|date| {
-                let offset = date.get().unwrap().offset().fix().local_minus_utc();
-                MappedLocalTime::Single(FixedOffset::east_opt(offset).unwrap())
-            })
-        }
+                assert_eq!(event.day_after_night(3).len(), 3);
 
-        pub(super) fn offset_from_local_datetime(
-            _local_time: &NaiveDateTime,
-        ) -> MappedLocalTime<FixedOffset> {
-            DATE.with(|date| {
-                let offset = date.get().unwrap().offset().fix().local_minus_utc();
-                MappedLocalTime::Single(FixedOffset::east_opt(offset).unwrap())
-            })
-        }
-    }
+                // With periods, night may start after midnight
+                let event = ModeScheduler::new(
+                    Schedule {
+                        day: ScheduleType::Fixed(NaiveTime::from_hms_opt(8, 0, 0).unwrap()),
+                        night: ScheduleType::Fixed(NaiveTime::from_hms_opt(0, 30, 0).unwrap()),
+                        periods: vec![ScheduleType::Auto],
+                        transition: TimeDelta::zero(),
+                        polar_fallback: PolarFallback::Sun,
+                        zone: Zone::Local,
+                        weekdays: [None; 7],
+                        exceptions: Vec::new(),
+                    },
+                    NAIROBI_LOCATION,
+                )
+                .unwrap();
+                assert!(event.day_after_night(3).is_empty());
+            }
 
-    #[derive(Clone)]
-    pub struct Local;
+            #[test]
+            fn elevation() {
+                set_time(13, 0, NAIROBI_OFFSET);
+                let event = ModeScheduler::new(
+                    Schedule {
+                        day: ScheduleType::Elevation(3.0),
+                        night: ScheduleType::Elevation(-6.0),
+                        periods: Vec::new(),
+                        transition: TimeDelta::zero(),
+                        polar_fallback: PolarFallback::Sun,
+                        zone: Zone::Local,
+                        weekdays: [None; 7],
+                        exceptions: Vec::new(),
+                    },
+                    NAIROBI_LOCATION,
+                )
+                .unwrap();
 
-    impl Local {
-        pub fn now() -> DateTime<chrono::Local> {
-            DATE.with(|date| {
-                let localdate: DateTime<chrono::Local> = date.get().unwrap().into();
-                let offset = date.get().unwrap().offset().fix();
-                DateTime::from_naive_utc_and_offset(localdate.naive_utc(), offset)
-            })
+                assert!(event.timeline(1).is_none());
+            }
         }
-    }
 
-    impl TimeZone for Local {
-        type Offset = FixedOffset;
+        mod auto_fixed {
+            use super::*;
 
-        fn from_offset(_offset: &FixedOffset) -> Local {
-            Local
-        }
+            const OFFSET: FixedOffset = NAIROBI_OFFSET;
 
-        #[allow(deprecated)]
-        fn offset_from_local_date(&self, local: &NaiveDate) -> MappedLocalTime<FixedOffset> {
-            // Get the offset at local midnight.
-            self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
-        }
+            #[test]
+            fn day_auto_night_fixed() {
+                set_time(0, 0, OFFSET);
+                let mut event = ModeScheduler::new(
+                    Schedule {
+                        day: ScheduleType::Auto,
+                        night: ScheduleType::Fixed(NaiveTime::from_hms_opt(19, 0, 0).unwrap()),
+                        periods: Vec::new(),
+                        transition: TimeDelta::zero(),
+                        polar_fallback: PolarFallback::Sun,
+                        zone: Zone::Local,
+                        weekdays: [None; 7],
+                        exceptions: Vec::new(),
+                    },
+                    NAIROBI_LOCATION,
+                )
+                .unwrap();
 
-        fn offset_from_local_datetime(
-            &self,
-            local: &NaiveDateTime,
-        ) -> MappedLocalTime<FixedOffset> {
-            inner::offset_from_local_datetime(local)
-        }
+                assert_next_event(&mut event, ColorMode::Night, 6, 15..45, &OFFSET);
+                assert_next_event(&mut event, ColorMode::Day, 19, 0..1, &OFFSET);
+            }
 
-        #[allow(deprecated)]
-        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
-            // Get the offset at midnight.
-            self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
-        }
+            #[test]
+            fn day_fixed_night_auto() {
+                set_time(0, 0, OFFSET);
+                let mut event = ModeScheduler::new(
+                    Schedule {
+                        day: ScheduleType::Fixed(NaiveTime::from_hms_opt(7, 0, 0).unwrap()),
+                        night: ScheduleType::Auto,
+                        periods: Vec::new(),
+                        transition: TimeDelta::zero(),
+                        polar_fallback: PolarFallback::Sun,
+                        zone: Zone::Local,
+                        weekdays: [None; 7],
+                        exceptions: Vec::new(),
+                    },
+                    NAIROBI_LOCATION,
+                )
+                .unwrap();
 
-        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
-            inner::offset_from_utc_datetime(utc).unwrap()
+                assert_next_event(&mut event, ColorMode::Night, 7, 0..1, &OFFSET);
+                assert_next_event(&mut event, ColorMode::Day, 18, 15..45, &OFFSET);
+            }
         }
     }
-
-    pub fn set(val: DateTime<chrono::FixedOffset>) {
-        DATE.with(|date| date.set(Some(val)));
-    }
 }
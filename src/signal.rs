@@ -0,0 +1,70 @@
+use std::{
+    fs::File,
+    io::Read,
+    os::fd::{AsFd, BorrowedFd, FromRawFd, OwnedFd},
+};
+
+/// Receives blocked signals through a file descriptor, so they can be polled with other events
+pub struct SignalFd {
+    file: File,
+}
+
+impl SignalFd {
+    /// Blocks `signals` for the calling thread and threads spawned after this call
+    pub fn new(signals: &[libc::c_int]) -> std::io::Result<Self> {
+        let mut mask = unsafe { std::mem::zeroed::<libc::sigset_t>() };
+        unsafe { libc::sigemptyset(&mut mask) };
+        for &signal in signals {
+            unsafe { libc::sigaddset(&mut mask, signal) };
+        }
+
+        let errno = unsafe { libc::pthread_sigmask(libc::SIG_BLOCK, &mask, std::ptr::null_mut()) };
+        if errno != 0 {
+            return Err(std::io::Error::from_raw_os_error(errno));
+        }
+
+        let fd = unsafe { libc::signalfd(-1, &mask, libc::SFD_CLOEXEC) };
+        if fd == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Self {
+            file: File::from(unsafe { OwnedFd::from_raw_fd(fd) }),
+        })
+    }
+
+    /// Reads the next pending signal, blocking until one arrives
+    pub fn read(&mut self) -> std::io::Result<libc::c_int> {
+        let mut info = [0; size_of::<libc::signalfd_siginfo>()];
+        self.file.read_exact(&mut info)?;
+        // `ssi_signo` is the first field of `signalfd_siginfo`
+        Ok(u32::from_ne_bytes([info[0], info[1], info[2], info[3]]) as libc::c_int)
+    }
+}
+
+impl AsFd for SignalFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+pub fn signal_name(signal: libc::c_int) -> &'static str {
+    match signal {
+        libc::SIGINT => "SIGINT",
+        libc::SIGTERM => "SIGTERM",
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGUSR1 => "SIGUSR1",
+        _ => "unknown signal",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn receive_signal() {
+        let mut signalfd = SignalFd::new(&[libc::SIGWINCH]).unwrap();
+        unsafe { libc::raise(libc::SIGWINCH) };
+        assert_eq!(signalfd.read().unwrap(), libc::SIGWINCH);
+    }
+}
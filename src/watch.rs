@@ -0,0 +1,131 @@
+use std::{
+    ffi::{CString, OsString},
+    fs::File,
+    io::Read,
+    os::{
+        fd::{AsFd, BorrowedFd, FromRawFd, OwnedFd},
+        unix::ffi::{OsStrExt, OsStringExt},
+    },
+    path::Path,
+};
+
+/// Watches the directory of a file for the file being written or replaced
+///
+/// The directory is watched rather than the file itself, as editors commonly save by replacing
+/// the file, which would silently end a watch on the file.
+pub struct FileWatcher {
+    file: File,
+    file_name: OsString,
+}
+
+impl FileWatcher {
+    pub fn new(path: &Path) -> std::io::Result<Self> {
+        Self::with_mask(path, libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO)
+    }
+
+    /// Watches a symbolic link, which is replaced by creating a new link rather than written
+    pub fn new_link(path: &Path) -> std::io::Result<Self> {
+        Self::with_mask(path, libc::IN_CREATE | libc::IN_MOVED_TO)
+    }
+
+    fn with_mask(path: &Path, mask: u32) -> std::io::Result<Self> {
+        let file_name = path
+            .file_name()
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidInput))?
+            .to_owned();
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let directory = CString::new(directory.as_os_str().as_bytes())?;
+
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC | libc::IN_NONBLOCK) };
+        if fd == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let file = File::from(unsafe { OwnedFd::from_raw_fd(fd) });
+
+        if unsafe { libc::inotify_add_watch(fd, directory.as_ptr(), mask) } == -1 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(Self { file, file_name })
+    }
+
+    /// Drains pending events and returns whether any of them concerns the watched file
+    pub fn changed(&mut self) -> std::io::Result<bool> {
+        const HEADER_SIZE: usize = size_of::<libc::inotify_event>();
+
+        let mut changed = false;
+        let mut buffer = [0; 4096];
+        loop {
+            let size = match self.file.read(&mut buffer) {
+                Ok(size) => size,
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => return Ok(changed),
+                Err(err) => return Err(err),
+            };
+
+            let mut offset = 0;
+            while offset + HEADER_SIZE <= size {
+                // `len` is the last field of the `inotify_event` header
+                let len = u32::from_ne_bytes(
+                    buffer[offset + HEADER_SIZE - 4..offset + HEADER_SIZE]
+                        .try_into()
+                        .unwrap(),
+                ) as usize;
+                let name = &buffer[offset + HEADER_SIZE..offset + HEADER_SIZE + len];
+                let name: Vec<u8> = name.iter().copied().take_while(|&b| b != 0).collect();
+                changed |= OsString::from_vec(name) == self.file_name;
+                offset += HEADER_SIZE + len;
+            }
+        }
+    }
+}
+
+impl AsFd for FileWatcher {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn file_changed() {
+        let directory = std::env::temp_dir().join(format!("wl-nightlight-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("config.toml");
+
+        let mut watcher = FileWatcher::new(&path).unwrap();
+        assert!(!watcher.changed().unwrap());
+
+        std::fs::write(directory.join("other.toml"), "").unwrap();
+        assert!(!watcher.changed().unwrap());
+
+        std::fs::write(&path, "").unwrap();
+        assert!(watcher.changed().unwrap());
+        assert!(!watcher.changed().unwrap());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn link_replaced() {
+        let directory =
+            std::env::temp_dir().join(format!("wl-nightlight-link-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("localtime");
+        std::os::unix::fs::symlink("/usr/share/zoneinfo/UTC", &path).unwrap();
+
+        let mut watcher = FileWatcher::new_link(&path).unwrap();
+        assert!(!watcher.changed().unwrap());
+
+        std::fs::remove_file(&path).unwrap();
+        std::os::unix::fs::symlink("/usr/share/zoneinfo/Europe/London", &path).unwrap();
+        assert!(watcher.changed().unwrap());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
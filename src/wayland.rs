@@ -2,12 +2,19 @@
 use serial_test::serial;
 
 use std::{
-    os::fd::AsFd,
-    sync::mpsc::{Receiver, Sender},
+    fs::File,
+    io::{Read, Write},
+    ops::ControlFlow,
+    os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd},
+    sync::{
+        Arc,
+        mpsc::{self, Receiver, Sender, TryRecvError},
+    },
 };
 
 use wayland_client::{
-    Connection, Dispatch, Proxy, QueueHandle,
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle,
+    backend::WaylandError,
     protocol::{
         wl_output::{self, WlOutput},
         wl_registry,
@@ -21,24 +28,43 @@ use wayland_protocols_wlr::gamma_control::v1::client::{
 use crate::{
     InternalError,
     color::{Color, fill_color_ramp},
+    lut::Lut,
+    output::{OutputColors, OutputInfo},
 };
 
 pub enum WaylandRequest {
-    ChangeOutputColor(Color),
+    ChangeOutputColor(OutputColors),
+    /// Releases every gamma control so the compositor restores the original gamma, then stops
+    Shutdown,
+}
+
+/// Sends requests to the Wayland thread, waking it up from polling the Wayland socket
+pub struct RequestSender {
+    sender: Sender<WaylandRequest>,
+    eventfd: File,
+}
+
+impl RequestSender {
+    pub fn send(&self, request: WaylandRequest) -> anyhow::Result<()> {
+        self.sender
+            .send(request)
+            .map_err(|_| anyhow::anyhow!("Wayland thread receiver dropped"))?;
+        (&self.eventfd).write_all(&1u64.to_ne_bytes())?;
+        Ok(())
+    }
 }
 
 pub struct Wayland {
     connection: Connection,
+    event_queue: EventQueue<WaylandState>,
     state: WaylandState,
     sender: Sender<anyhow::Result<()>>,
     receiver: Receiver<WaylandRequest>,
+    eventfd: File,
 }
 
 impl Wayland {
-    pub fn new(
-        sender: Sender<anyhow::Result<()>>,
-        receiver: Receiver<WaylandRequest>,
-    ) -> anyhow::Result<Self> {
+    pub fn new(sender: Sender<anyhow::Result<()>>) -> anyhow::Result<(Self, RequestSender)> {
         let connection = Connection::connect_to_env()?;
 
         let display = connection.display();
@@ -55,61 +81,146 @@ impl Wayland {
                 "Your Wayland compositor is not supported because it does not implement the wlr-gamma-control-unstable-v1 protocol"
             )
         }
-
-        if let Some(gamma_manager) = &state.gamma_manager {
-            for output in &mut state.outputs {
-                let wl_output = &output.wl_output;
-                output.gamma_control = Some(gamma_manager.get_gamma_control(wl_output, &qh, ()));
-            }
-        }
         event_queue.roundtrip(&mut state)?;
 
         if state.outputs.is_empty() {
             anyhow::bail!("No output found")
         }
 
-        Ok(Self {
-            connection,
-            state,
-            sender,
-            receiver,
-        })
+        let eventfd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if eventfd == -1 {
+            Err(std::io::Error::last_os_error())?;
+        }
+        let eventfd = File::from(unsafe { OwnedFd::from_raw_fd(eventfd) });
+
+        let (request_sender, receiver) = mpsc::channel();
+        let request_sender = RequestSender {
+            sender: request_sender,
+            eventfd: eventfd.try_clone()?,
+        };
+
+        Ok((
+            Self {
+                connection,
+                event_queue,
+                state,
+                sender,
+                receiver,
+                eventfd,
+            },
+            request_sender,
+        ))
     }
 
+    /// Dispatches Wayland events and processes requests until the request sender is dropped
     pub fn process_requests(&mut self) {
         let result = (|| -> anyhow::Result<()> {
-            while let Ok(request) = self.receiver.recv() {
-                self.connection
-                    .new_event_queue()
-                    .roundtrip(&mut self.state)?;
-
-                match request {
-                    WaylandRequest::ChangeOutputColor(color) => {
-                        for output in self.state.outputs.iter_mut() {
-                            output.set_color(color)?;
-                        }
+            loop {
+                self.connection.flush()?;
+                let Some(guard) = self.event_queue.prepare_read() else {
+                    self.event_queue.dispatch_pending(&mut self.state)?;
+                    continue;
+                };
+
+                let mut poll_array = [
+                    libc::pollfd {
+                        fd: guard.connection_fd().as_raw_fd(),
+                        events: libc::POLLIN,
+                        revents: 0,
+                    },
+                    libc::pollfd {
+                        fd: self.eventfd.as_raw_fd(),
+                        events: libc::POLLIN,
+                        revents: 0,
+                    },
+                ];
+                if unsafe { libc::poll(poll_array.as_mut_ptr(), poll_array.len() as _, -1) } == -1 {
+                    let err = std::io::Error::last_os_error();
+                    if err.kind() == std::io::ErrorKind::Interrupted {
+                        continue;
                     }
+                    Err(err)?;
                 }
 
-                self.connection.flush()?;
-                self.sender
-                    .send(Ok(()))
-                    .expect("Main thread receiver dropped");
+                if poll_array[0].revents != 0 {
+                    match guard.read() {
+                        Err(WaylandError::Io(err))
+                            if err.kind() == std::io::ErrorKind::WouldBlock => {}
+                        result => {
+                            result?;
+                        }
+                    }
+                } else {
+                    drop(guard);
+                }
+                self.event_queue.dispatch_pending(&mut self.state)?;
+
+                if poll_array[1].revents != 0 {
+                    self.eventfd.read_exact(&mut [0; 8])?;
+                    loop {
+                        match self.receiver.try_recv() {
+                            Ok(request) => {
+                                if self.process_request(request)?.is_break() {
+                                    return Ok(());
+                                }
+                            }
+                            Err(TryRecvError::Empty) => break,
+                            Err(TryRecvError::Disconnected) => return Ok(()),
+                        }
+                    }
+                }
             }
-
-            Ok(())
         })();
 
         self.sender
             .send(result)
             .expect("Main thread receiver dropped");
     }
+
+    fn shutdown(&mut self) -> anyhow::Result<()> {
+        for output in self.state.outputs.drain(..) {
+            if output.gamma_control.is_some() {
+                log::info!(
+                    "Restore gamma of output {} ({})",
+                    output.registry_name,
+                    output.info.name.as_deref().unwrap_or("unnamed")
+                );
+            }
+            output.destroy();
+        }
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    fn process_request(&mut self, request: WaylandRequest) -> anyhow::Result<ControlFlow<()>> {
+        match request {
+            WaylandRequest::ChangeOutputColor(colors) => {
+                let qh = self.event_queue.handle();
+                for output in self.state.outputs.iter_mut() {
+                    output.apply(&colors, self.state.gamma_manager.as_ref(), &qh)?;
+                }
+                self.state.colors = Some(colors);
+            }
+            WaylandRequest::Shutdown => {
+                self.shutdown()?;
+                return Ok(ControlFlow::Break(()));
+            }
+        }
+
+        self.connection.flush()?;
+        self.sender
+            .send(Ok(()))
+            .expect("Main thread receiver dropped");
+        Ok(ControlFlow::Continue(()))
+    }
 }
 
 #[cfg_attr(test, derive(Debug))]
 struct WaylandState {
     outputs: Vec<OutputDevice>,
     gamma_manager: Option<ZwlrGammaControlManagerV1>,
+    /// Last requested colors, applied to outputs as soon as their gamma size is known
+    colors: Option<OutputColors>,
 }
 
 impl WaylandState {
@@ -117,6 +228,7 @@ impl WaylandState {
         Self {
             gamma_manager: None,
             outputs: Vec::new(),
+            colors: None,
         }
     }
 }
@@ -125,10 +237,12 @@ impl WaylandState {
 struct OutputDevice {
     registry_name: u32,
     wl_output: WlOutput,
-    device_name: Option<String>,
+    info: OutputInfo,
     gamma_control: Option<ZwlrGammaControlV1>,
     gamma_size: usize,
     color: Color,
+    /// Lookup table applied on top of the color
+    lut: Option<Arc<Lut>>,
 }
 
 impl OutputDevice {
@@ -136,10 +250,11 @@ impl OutputDevice {
         Self {
             registry_name,
             wl_output,
-            device_name: None,
+            info: OutputInfo::default(),
             gamma_control: None,
             gamma_size: 0,
             color: Color::default(),
+            lut: None,
         }
     }
 
@@ -167,6 +282,9 @@ impl OutputDevice {
         let (r, rest) = buf.split_at_mut(self.gamma_size);
         let (g, b) = rest.split_at_mut(self.gamma_size);
         fill_color_ramp(r, g, b, self.gamma_size, self.color);
+        if let Some(lut) = &self.lut {
+            lut.apply(r, g, b);
+        }
         self.gamma_control
             .as_ref()
             .ok_or(InternalError {
@@ -177,14 +295,53 @@ impl OutputDevice {
         Ok(())
     }
 
-    fn set_color(&mut self, color: Color) -> anyhow::Result<()> {
-        if self.color != color {
+    fn set_color(&mut self, color: Color, lut: Option<Arc<Lut>>) -> anyhow::Result<()> {
+        let lut_changed = match (&self.lut, &lut) {
+            (Some(current), Some(lut)) => !Arc::ptr_eq(current, lut),
+            (current, lut) => current.is_some() != lut.is_some(),
+        };
+        if self.color != color || lut_changed {
             self.color = color;
+            self.lut = lut;
             self.update_gamma()?;
         }
 
         Ok(())
     }
+
+    /// Applies the color matching this output, acquiring or releasing its gamma control as needed
+    fn apply(
+        &mut self,
+        colors: &OutputColors,
+        gamma_manager: Option<&ZwlrGammaControlManagerV1>,
+        qh: &QueueHandle<WaylandState>,
+    ) -> anyhow::Result<()> {
+        match colors.get(&self.info) {
+            Some(color) => match (&self.gamma_control, gamma_manager) {
+                (Some(_), _) => self.set_color(color, colors.lut(&self.info))?,
+                (None, Some(gamma_manager)) => {
+                    log::debug!("Acquire gamma control of output {}", self.registry_name);
+                    self.gamma_control =
+                        Some(gamma_manager.get_gamma_control(&self.wl_output, qh, ()));
+                }
+                (None, None) => (),
+            },
+            None => {
+                if let Some(gamma_control) = self.gamma_control.take() {
+                    log::info!(
+                        "Release gamma control of output {} as it is disabled",
+                        self.registry_name
+                    );
+                    gamma_control.destroy();
+                    self.gamma_size = 0;
+                    self.color = Color::default();
+                    self.lut = None;
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl Dispatch<wl_registry::WlRegistry, ()> for WaylandState {
@@ -204,15 +361,21 @@ impl Dispatch<wl_registry::WlRegistry, ()> for WaylandState {
             } => {
                 if interface == WlOutput::interface().name {
                     let wl_output = registry.bind::<WlOutput, _, _>(name, version, qh, ());
-                    state.outputs.push(OutputDevice::new(name, wl_output));
+                    let mut output = OutputDevice::new(name, wl_output);
+                    if let Some(gamma_manager) = &state.gamma_manager {
+                        output.gamma_control =
+                            Some(gamma_manager.get_gamma_control(&output.wl_output, qh, ()));
+                    }
+                    state.outputs.push(output);
                     log::debug!("Bind output {}", name);
                 } else if interface == ZwlrGammaControlManagerV1::interface().name {
-                    state.gamma_manager = Some(registry.bind::<ZwlrGammaControlManagerV1, _, _>(
-                        name,
-                        version,
-                        qh,
-                        (),
-                    ));
+                    let gamma_manager =
+                        registry.bind::<ZwlrGammaControlManagerV1, _, _>(name, version, qh, ());
+                    for output in &mut state.outputs {
+                        output.gamma_control =
+                            Some(gamma_manager.get_gamma_control(&output.wl_output, qh, ()));
+                    }
+                    state.gamma_manager = Some(gamma_manager);
                     log::debug!("Bind gamma control manager");
                 }
             }
@@ -234,16 +397,39 @@ impl Dispatch<WlOutput, ()> for WaylandState {
         event: <WlOutput as Proxy>::Event,
         _data: &(),
         _conn: &Connection,
-        _qh: &QueueHandle<Self>,
+        qh: &QueueHandle<Self>,
     ) {
-        if let wl_output::Event::Name { name } = event {
-            let output = state
-                .outputs
-                .iter_mut()
-                .find(|o| o.wl_output == *proxy)
-                .expect("Received event for unknown output");
-            log::debug!("New output {}, named {}", output.registry_name, name);
-            output.device_name = Some(name);
+        let output = state
+            .outputs
+            .iter_mut()
+            .find(|o| o.wl_output == *proxy)
+            .expect("Received event for unknown output");
+        match event {
+            wl_output::Event::Geometry { make, model, .. } => {
+                output.info.make = Some(make);
+                output.info.model = Some(model);
+            }
+            wl_output::Event::Name { name } => output.info.name = Some(name),
+            wl_output::Event::Description { description } => {
+                output.info.description = Some(description)
+            }
+            wl_output::Event::Done => {
+                log::debug!("Output {} has {}", output.registry_name, output.info);
+                // A gamma control without a size yet is applied to once `GammaSize` arrives
+                if output.gamma_control.is_some() && output.gamma_size == 0 {
+                    return;
+                }
+                if let Some(colors) = &state.colors
+                    && let Err(error) = output.apply(colors, state.gamma_manager.as_ref(), qh)
+                {
+                    log::error!(
+                        "Fail to set gamma of output {}, {}",
+                        output.registry_name,
+                        error
+                    );
+                }
+            }
+            _ => (),
         }
     }
 }
@@ -267,7 +453,7 @@ impl Dispatch<ZwlrGammaControlV1, ()> for WaylandState {
         event: <ZwlrGammaControlV1 as Proxy>::Event,
         _data: &(),
         _conn: &Connection,
-        _qh: &QueueHandle<Self>,
+        qh: &QueueHandle<Self>,
     ) {
         let index = state
             .outputs
@@ -278,11 +464,22 @@ impl Dispatch<ZwlrGammaControlV1, ()> for WaylandState {
             zwlr_gamma_control_v1::Event::GammaSize { size } => {
                 let output = &mut state.outputs[index];
                 output.gamma_size = size as usize;
+                output.color = Color::default();
+                output.lut = None;
                 log::debug!(
                     "New gamma control for output {}, gamma size is {}",
                     output.registry_name,
                     size
                 );
+                if let Some(colors) = &state.colors
+                    && let Err(error) = output.apply(colors, state.gamma_manager.as_ref(), qh)
+                {
+                    log::error!(
+                        "Fail to set gamma of output {}, {}",
+                        output.registry_name,
+                        error
+                    );
+                }
             }
             zwlr_gamma_control_v1::Event::Failed => {
                 let output = state.outputs.swap_remove(index);
@@ -301,16 +498,11 @@ impl Dispatch<ZwlrGammaControlV1, ()> for WaylandState {
 #[serial]
 mod tests {
     use super::*;
-    use std::sync::mpsc;
-
-    fn get_wayland() -> anyhow::Result<(
-        Wayland,
-        Receiver<anyhow::Result<()>>,
-        Sender<WaylandRequest>,
-    )> {
-        let (req_sender, req_receiver) = mpsc::channel();
+    use crate::color::WhitePointModel;
+
+    fn get_wayland() -> anyhow::Result<(Wayland, Receiver<anyhow::Result<()>>, RequestSender)> {
         let (res_sender, res_receiver) = mpsc::channel();
-        let wayland = Wayland::new(res_sender, req_receiver)?;
+        let (wayland, req_sender) = Wayland::new(res_sender)?;
         Ok((wayland, res_receiver, req_sender))
     }
 
@@ -326,11 +518,19 @@ mod tests {
         let (mut wayland, receiver, sender) = get_wayland().unwrap();
 
         sender
-            .send(WaylandRequest::ChangeOutputColor(Color {
-                temperature: 1000,
-                gamma: 0.1,
-                brightness: 0.1,
-                inverted: true,
+            .send(WaylandRequest::ChangeOutputColor(OutputColors {
+                default: Color {
+                    temperature: 1000,
+                    gamma: [0.1, 0.2, 0.3],
+                    brightness: 0.1,
+                    inverted: true,
+                    gain: [1.0, 0.9, 0.8],
+                    offset: [0.0, 0.0, 0.1],
+                    white_point_model: WhitePointModel::Daylight,
+                },
+                outputs: Vec::new(),
+                luts: Vec::new(),
+                selected: Vec::new(),
             }))
             .unwrap();
 
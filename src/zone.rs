@@ -0,0 +1,139 @@
+#[cfg(not(test))]
+use chrono::Local;
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Offset, TimeDelta, Utc};
+
+#[cfg(test)]
+use crate::clock::mock_chrono::Local;
+
+/// Time zone the dates and fixed times of the schedule are interpreted in
+#[derive(Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub enum Zone {
+    /// Time zone of the session, following changes to it
+    Local,
+    /// Zone loaded from the system tzdata, e.g. `Europe/London`
+    Named(tz::TimeZone),
+}
+
+impl Zone {
+    /// Loads the zone `name` from the system tzdata
+    pub fn named(name: &str) -> Option<Self> {
+        tz::TimeZone::from_posix_tz(name).ok().map(Self::Named)
+    }
+
+    /// Returns the offset from UTC in effect at `date_time`
+    pub fn offset(&self, date_time: DateTime<Utc>) -> FixedOffset {
+        match self {
+            Self::Local => date_time.with_timezone(&Local).offset().fix(),
+            Self::Named(time_zone) => time_zone
+                .find_local_time_type(date_time.timestamp())
+                .ok()
+                .and_then(|local_time_type| FixedOffset::east_opt(local_time_type.ut_offset()))
+                .unwrap_or(Utc.fix()),
+        }
+    }
+
+    /// Returns the local date at `date_time`
+    pub fn date(&self, date_time: DateTime<Utc>) -> NaiveDate {
+        date_time
+            .with_timezone(&self.offset(date_time))
+            .date_naive()
+    }
+
+    /// Converts a local date and time to UTC
+    ///
+    /// Times repeated when the clocks go back resolve to their first occurrence, times skipped
+    /// when the clocks go forward are moved forward by the length of the gap.
+    pub fn resolve(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        let to_utc = |offset: FixedOffset| {
+            local.and_utc() - TimeDelta::seconds(offset.local_minus_utc() as i64)
+        };
+        // Offsets change at most once a day, so the ones a day apart are the only candidates
+        let before = self.offset(local.and_utc() - TimeDelta::days(1));
+        let after = self.offset(local.and_utc() + TimeDelta::days(1));
+        [before, after]
+            .into_iter()
+            .map(|offset| (offset, to_utc(offset)))
+            .filter(|(offset, date_time)| self.offset(*date_time) == *offset)
+            .map(|(_, date_time)| date_time)
+            .min()
+            // In a gap, the offset before it shifts the time past the transition
+            .unwrap_or_else(|| to_utc(before))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use super::*;
+    use crate::clock::mock_chrono;
+
+    fn local(year: i32, month: u32, day: u32, hour: u32, min: u32) -> NaiveDateTime {
+        NaiveDateTime::new(
+            NaiveDate::from_ymd_opt(year, month, day).unwrap(),
+            NaiveTime::from_hms_opt(hour, min, 0).unwrap(),
+        )
+    }
+
+    #[test]
+    fn named() {
+        assert!(Zone::named("Europe/London").is_some());
+        assert!(Zone::named("Europe/Nowhere").is_none());
+
+        let london = Zone::named("Europe/London").unwrap();
+        assert_eq!(
+            london.resolve(local(2000, 1, 15, 12, 0)),
+            local(2000, 1, 15, 12, 0).and_utc()
+        );
+        assert_eq!(
+            london.resolve(local(2000, 7, 15, 12, 0)),
+            local(2000, 7, 15, 11, 0).and_utc()
+        );
+        assert_eq!(
+            london.date(local(2000, 7, 15, 23, 30).and_utc()),
+            NaiveDate::from_ymd_opt(2000, 7, 16).unwrap()
+        );
+    }
+
+    #[test]
+    fn named_transitions() {
+        let london = Zone::named("Europe/London").unwrap();
+        // Clocks go forward from 01:00 to 02:00 on 2000-03-26
+        assert_eq!(
+            london.resolve(local(2000, 3, 26, 1, 30)),
+            local(2000, 3, 26, 1, 30).and_utc()
+        );
+        assert_eq!(
+            london.resolve(local(2000, 3, 26, 2, 0)),
+            local(2000, 3, 26, 1, 0).and_utc()
+        );
+        // Clocks go back from 02:00 to 01:00 on 2000-10-29
+        assert_eq!(
+            london.resolve(local(2000, 10, 29, 1, 30)),
+            local(2000, 10, 29, 0, 30).and_utc()
+        );
+        assert_eq!(
+            london.resolve(local(2000, 10, 29, 2, 0)),
+            local(2000, 10, 29, 2, 0).and_utc()
+        );
+    }
+
+    #[test]
+    fn local_zone() {
+        mock_chrono::set(
+            local(2000, 1, 1, 12, 0)
+                .and_local_timezone(FixedOffset::east_opt(3600 * 9).unwrap())
+                .unwrap(),
+        );
+        assert_eq!(
+            Zone::Local.resolve(local(2000, 1, 1, 8, 0)),
+            local(1999, 12, 31, 23, 0).and_utc()
+        );
+        assert_eq!(
+            Zone::Local.date(local(1999, 12, 31, 23, 0).and_utc()),
+            NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()
+        );
+    }
+}